@@ -1,24 +1,211 @@
-use std::process::Command;
-use std::path::Path;
 use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// The Wren release these bindings are written against.
+const WREN_VERSION: (u32, u32) = (0, 4);
 
 fn main() {
-	let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
-	let manifest_path = Path::new(&manifest_dir);
+	println!("cargo:rerun-if-env-changed=WREN_LIB_DIR");
+	println!("cargo:rerun-if-env-changed=WREN_INCLUDE_DIR");
+	println!("cargo:rerun-if-env-changed=WREN_STATIC");
 
-	let wren_lib_dir = manifest_path.join("wren/lib");
-	let wren_make_dir = if cfg!(target_os = "macos") {
-		manifest_path.join("wren/projects/make.mac")
+	let include_paths = if let Some(lib_dir) = env::var_os("WREN_LIB_DIR") {
+		link_lib_dir(Path::new(&lib_dir))
+	} else if env::var_os("CARGO_FEATURE_SYSTEM").is_some() {
+		link_pkg_config()
 	} else {
-		manifest_path.join("wren/projects/make")
+		build_vendored()
+	};
+
+	#[cfg(feature = "bindgen")]
+	generate_bindings(&include_paths);
+	#[cfg(not(feature = "bindgen"))]
+	let _ = include_paths;
+}
+
+fn wants_static() -> bool {
+	match env::var("WREN_STATIC") {
+		Ok(value) => value != "0",
+		Err(_) => false,
+	}
+}
+
+// Links against a prebuilt libwren found in [lib_dir].
+fn link_lib_dir(lib_dir: &Path) -> Vec<PathBuf> {
+	let include_dir = match env::var_os("WREN_INCLUDE_DIR") {
+		Some(dir) => PathBuf::from(dir),
+		None => lib_dir.join("../include"),
 	};
+	let include_paths = vec![include_dir];
+	check_header(&include_paths);
+
+	let kind = if wants_static() { "static" } else { "dylib" };
+	println!("cargo:rustc-link-search=native={}", lib_dir.display());
+	println!("cargo:rustc-link-lib={}=wren", kind);
+	include_paths
+}
+
+#[cfg(feature = "system")]
+fn link_pkg_config() -> Vec<PathBuf> {
+	let library = pkg_config::Config::new()
+		.statik(wants_static())
+		.probe("wren")
+		.unwrap_or_else(|err| panic!("could not find a system libwren: {}", err));
+
+	let version = parse_version(&library.version)
+		.unwrap_or_else(|| panic!("could not read the Wren version from pkg-config: {:?}", library.version));
+	check_version("the system libwren", version);
+
+	// pkg-config leaves out the compiler's default include directories, so the
+	// header is only checked as well when it can be found.
+	let mut include_paths = library.include_paths;
+	if let Some(dir) = env::var_os("WREN_INCLUDE_DIR") {
+		include_paths.insert(0, PathBuf::from(dir));
+	}
+	if let Some(header) = find_header(&include_paths) {
+		check_header_version(&header);
+	}
+	include_paths
+}
+
+#[cfg(not(feature = "system"))]
+fn link_pkg_config() -> Vec<PathBuf> {
+	unreachable!();
+}
+
+// Makes sure the `wren.h` in [include_paths] matches [WREN_VERSION]. Without
+// one the version can't be checked, so that fails the build too.
+fn check_header(include_paths: &[PathBuf]) {
+	let header = find_header(include_paths).unwrap_or_else(|| {
+		let dirs: Vec<String> = include_paths.iter().map(|dir| dir.display().to_string()).collect();
+		panic!(
+			"wren.h not found in {}; set WREN_INCLUDE_DIR to the directory containing it",
+			dirs.join(", ")
+		)
+	});
+	check_header_version(&header);
+}
+
+fn find_header(include_paths: &[PathBuf]) -> Option<PathBuf> {
+	include_paths
+		.iter()
+		.map(|dir| dir.join("wren.h"))
+		.find(|path| path.exists())
+}
+
+fn check_header_version(header: &Path) {
+	println!("cargo:rerun-if-changed={}", header.display());
+
+	let source = fs::read_to_string(header).unwrap();
+	let define = |name: &str| -> Option<u32> {
+		source.lines().find_map(|line| {
+			let mut words = line.split_whitespace();
+			match (words.next(), words.next(), words.next()) {
+				(Some("#define"), Some(word), Some(value)) if word == name => value.parse().ok(),
+				_ => None,
+			}
+		})
+	};
+
+	let found = (
+		define("WREN_VERSION_MAJOR"),
+		define("WREN_VERSION_MINOR"),
+		define("WREN_VERSION_PATCH"),
+	);
+	match found {
+		(Some(major), Some(minor), Some(patch)) => {
+			check_version(&header.display().to_string(), (major, minor, patch))
+		}
+		_ => panic!("could not read the Wren version from {}", header.display()),
+	}
+}
+
+// Parses a version like `0.4.0` as reported by pkg-config.
+#[cfg(feature = "system")]
+fn parse_version(version: &str) -> Option<(u32, u32, u32)> {
+	let mut parts = version.trim().split('.').map(|part| part.parse().ok());
+	match (parts.next(), parts.next(), parts.next()) {
+		(Some(Some(major)), Some(Some(minor)), None) => Some((major, minor, 0)),
+		(Some(Some(major)), Some(Some(minor)), Some(Some(patch))) => Some((major, minor, patch)),
+		_ => None,
+	}
+}
+
+// Fails the build if [found], the version of the Wren in [what], isn't
+// [WREN_VERSION]. The full version is passed on to the layout tests as
+// `WREN_FOUND_VERSION`.
+fn check_version(what: &str, found: (u32, u32, u32)) {
+	let (major, minor, patch) = found;
+	if (major, minor) != WREN_VERSION {
+		panic!(
+			"{} is Wren {}.{}, but wren-sys requires Wren {}.{}",
+			what, major, minor, WREN_VERSION.0, WREN_VERSION.1
+		);
+	}
+	println!("cargo:rustc-env=WREN_FOUND_VERSION={}.{}.{}", major, minor, patch);
+}
+
+// Compiles the Wren sources from the submodule into a static library.
+fn build_vendored() -> Vec<PathBuf> {
+	let manifest_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
+	let manifest_path = Path::new(&manifest_dir);
+
+	let wren_src_dir = manifest_path.join("wren/src");
+	if !wren_src_dir.join("include/wren.h").exists() {
+		panic!(
+			"Wren sources not found in {}; run `git submodule update --init`",
+			wren_src_dir.display()
+		);
+	}
+	check_header(&[wren_src_dir.join("include")]);
+
+	let mut build = cc::Build::new();
+	build
+		.include(wren_src_dir.join("include"))
+		.include(wren_src_dir.join("vm"))
+		.include(wren_src_dir.join("optional"))
+		.flag_if_supported("-std=c99")
+		.warnings(false);
+
+	// Wren's optional modules default to on, so always define them explicitly.
+	for (feature, define) in &[("META", "WREN_OPT_META"), ("RANDOM", "WREN_OPT_RANDOM")] {
+		let enabled = env::var_os(format!("CARGO_FEATURE_{}", feature)).is_some();
+		build.define(define, if enabled { "1" } else { "0" });
+	}
+
+	for dir in &["vm", "optional"] {
+		let dir = wren_src_dir.join(dir);
+		for entry in fs::read_dir(&dir).unwrap() {
+			let path = entry.unwrap().path();
+			if path.extension().is_some_and(|ext| ext == "c") {
+				build.file(&path);
+			}
+		}
+		println!("cargo:rerun-if-changed={}", dir.display());
+	}
+	println!("cargo:rerun-if-changed={}", wren_src_dir.join("include").display());
+
+	// Emits `cargo:rustc-link-lib=static=wren` and the search path for OUT_DIR.
+	build.compile("wren");
+	vec![wren_src_dir.join("include")]
+}
+
+// Regenerates the raw bindings from the `wren.h` found in [include_paths] into
+// OUT_DIR, where `src/lib.rs` includes them as the `bindings` module.
+#[cfg(feature = "bindgen")]
+fn generate_bindings(include_paths: &[PathBuf]) {
+	let header = find_header(include_paths).expect("the bindgen feature requires wren.h; set WREN_INCLUDE_DIR");
 
-	let status = Command::new("make")
-		.current_dir(wren_make_dir)
-		.status();
-		
-	assert!(status.unwrap().success());
+	let bindings = bindgen::Builder::default()
+		.header(header.to_str().unwrap())
+		.allowlist_function("wren.*")
+		.allowlist_type("Wren.*")
+		.allowlist_var("WREN_.*")
+		.parse_callbacks(Box::new(bindgen::CargoCallbacks::new()))
+		.generate()
+		.expect("unable to generate bindings for wren.h");
 
-	println!("cargo:rustc-link-lib=static=wren");
-    println!("cargo:rustc-link-search={}", wren_lib_dir.display());
-}
\ No newline at end of file
+	let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+	bindings.write_to_file(out_dir.join("bindings.rs")).unwrap();
+}
@@ -0,0 +1,83 @@
+use wren_sys::wren::{wren_methods, ForeignRegistry, VmBuilder, WrenClass};
+
+#[derive(Clone, WrenClass)]
+#[wren(module = "vector")]
+struct Vec3 {
+	x: f64,
+	y: f64,
+	z: f64,
+}
+
+#[wren_methods]
+impl Vec3 {
+	#[wren(construct)]
+	fn new(x: f64, y: f64, z: f64) -> Self {
+		Vec3 { x, y, z }
+	}
+
+	#[wren(getter)]
+	fn zero() -> Self {
+		Vec3::new(0.0, 0.0, 0.0)
+	}
+
+	#[wren(getter)]
+	fn x(&self) -> f64 {
+		self.x
+	}
+
+	#[wren(setter)]
+	fn set_x(&mut self, x: f64) {
+		self.x = x;
+	}
+
+	#[wren(getter)]
+	fn length(&self) -> f64 {
+		(self.x * self.x + self.y * self.y + self.z * self.z).sqrt()
+	}
+
+	#[wren(operator = "+")]
+	fn add(&self, other: &Vec3) -> Vec3 {
+		Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+	}
+
+	#[wren(operator = "-")]
+	fn neg(&self) -> Self {
+		Vec3::new(-self.x, -self.y, -self.z)
+	}
+
+	#[wren(index)]
+	fn component(&self, index: usize) -> Result<f64, String> {
+		match index {
+			0 => Ok(self.x),
+			1 => Ok(self.y),
+			2 => Ok(self.z),
+			_ => Err(format!("Vec3 has no component {}", index)),
+		}
+	}
+
+	#[wren(getter, name = "toString")]
+	fn describe(&self) -> String {
+		format!("({}, {}, {})", self.x, self.y, self.z)
+	}
+}
+
+const SOURCE: &str = r#"
+import "vector" for Vec3
+
+var a = Vec3.new(1, 2, 2)
+a.x = 2
+var b = a + Vec3.zero + -Vec3.new(0, 0, 1)
+System.print("b = %(b), |a| = %(a.length), b[2] = %(b[2])")
+"#;
+
+fn main() {
+	let mut registry = ForeignRegistry::new();
+	registry.register::<Vec3>();
+
+	let mut vm = VmBuilder::new().foreign(registry).build();
+
+	match vm.interpret("main", SOURCE) {
+		Ok(()) => println!("Success"),
+		Err(err) => println!("{}", err),
+	}
+}
@@ -4,13 +4,13 @@ use std::mem::MaybeUninit;
 use std::ffi::{CStr, CString};
 use libc::{c_char, c_int};
 
-extern "C" fn write_fn(vm: *mut ffi::WrenVM, text: *const c_char) {
+extern "C" fn write_fn(_vm: *mut ffi::WrenVM, text: *const c_char) {
 	unsafe {
 		print!("{}", CStr::from_ptr(text).to_str().unwrap());
 	}
 }
 
-extern "C" fn error_fn(vm: *mut ffi::WrenVM, error_type: ffi::WrenErrorType, module: *const c_char, line: c_int, message: *const c_char) {
+extern "C" fn error_fn(_vm: *mut ffi::WrenVM, error_type: ffi::WrenErrorType, module: *const c_char, line: c_int, message: *const c_char) {
 	unsafe {
 		let module_str = CStr::from_ptr(module).to_str().unwrap();
 		let message_str = CStr::from_ptr(message).to_str().unwrap();
@@ -28,8 +28,8 @@ fn main() {
 		let mut config = MaybeUninit::<ffi::WrenConfiguration>::uninit();
 		ffi::wrenInitConfiguration(config.as_mut_ptr());
 
-		(*config.as_mut_ptr()).write_fn = write_fn;
-		(*config.as_mut_ptr()).error_fn = error_fn;
+		(*config.as_mut_ptr()).write_fn = Some(write_fn);
+		(*config.as_mut_ptr()).error_fn = Some(error_fn);
 
 		let vm = ffi::wrenNewVM(config.as_mut_ptr());
 
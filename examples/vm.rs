@@ -0,0 +1,35 @@
+use wren_sys::wren::{ForeignRegistry, VmBuilder};
+
+// `Math` is declared by the registry.
+const SOURCE: &str = r#"
+import "math" for Math
+
+System.print("I am running in a VM!")
+System.print("1 + 2 = %(Math.add(1, 2))")
+System.print("3 scaled = %(Math.scale(3))")
+"#;
+
+fn main() {
+	let mut registry = ForeignRegistry::new();
+	registry.method("math", "Math", true, "add(_,_)", |slots| {
+		let sum = slots.get::<f64>(1)? + slots.get::<f64>(2)?;
+		Ok(slots.set(0, sum)?)
+	});
+
+	let factor = 10.0;
+	registry.method("math", "Math", true, "scale(_)", move |slots| {
+		let value = slots.get::<f64>(1)?;
+		Ok(slots.set(0, value * factor)?)
+	});
+
+	let mut vm = VmBuilder::new()
+		.write(|text| print!("{}", text))
+		.initial_heap_size(1024 * 1024)
+		.foreign(registry)
+		.build();
+
+	match vm.interpret("my_module", SOURCE) {
+		Ok(()) => println!("Success"),
+		Err(err) => println!("{}", err),
+	}
+}
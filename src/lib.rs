@@ -3,6 +3,27 @@
 // extern crate libc;
 use libc::{c_void, size_t, c_char, c_int, c_double};
 
+// Bindings generated by bindgen from the `wren.h` that was built or linked
+// against. The hand-written declarations below are the crate's API; these are
+// kept around so that the two can be compared.
+#[cfg(feature = "bindgen")]
+#[allow(non_upper_case_globals, non_camel_case_types, dead_code, clippy::all)]
+pub mod bindings {
+	include!(concat!(env!("OUT_DIR"), "/bindings.rs"));
+}
+
+// The Wren semantic version number components.
+pub const WREN_VERSION_MAJOR: c_int = 0;
+pub const WREN_VERSION_MINOR: c_int = 4;
+pub const WREN_VERSION_PATCH: c_int = 0;
+
+// A human-friendly string representation of the version.
+pub const WREN_VERSION_STRING: &str = "0.4.0";
+
+// A monotonically increasing numeric representation of the version number. Use
+// this if you want to do range checks over versions.
+pub const WREN_VERSION_NUMBER: c_int = WREN_VERSION_MAJOR * 1000000 + WREN_VERSION_MINOR * 1000 + WREN_VERSION_PATCH;
+
 // A single virtual machine for executing Wren code.
 //
 // Wren has no global state, so all state stored by a running interpreter lives
@@ -33,7 +54,8 @@ pub struct WrenHandle;
 //
 // - To free memory, [memory] will be the memory to free and [newSize] will be
 //   zero. It should return NULL.
-pub type WrenReallocateFn = unsafe extern "C" fn(memory: *mut c_void, newSize: size_t) -> *mut c_void;
+#[allow(non_snake_case)]
+pub type WrenReallocateFn = unsafe extern "C" fn(memory: *mut c_void, newSize: size_t, userData: *mut c_void) -> *mut c_void;
 
 // A function callable from Wren code, but implemented in C.
 pub type WrenForeignMethodFn = unsafe extern "C" fn(vm: *mut WrenVM);
@@ -50,12 +72,29 @@ pub type WrenFinalizerFn = unsafe extern "C" fn(data: *mut c_void);
 // imports.
 pub type WrenResolveModuleFn = unsafe extern "C" fn(vm: *mut WrenVM, importer: *const c_char, name: *const c_char) -> *const c_char;
 
+// Called after loadModuleFn is called for module [name]. The original returned
+// result is handed back to you in this callback, so that you can free memory if
+// appropriate.
+pub type WrenLoadModuleCompleteFn = unsafe extern "C" fn(vm: *mut WrenVM, name: *const c_char, result: WrenLoadModuleResult);
+
+// The result of a loadModuleFn call.
+// [source] is the source code for the module, or NULL if the module is not found.
+// [onComplete] an optional callback that will be called once Wren is done with the result.
+#[derive(Copy, Clone)]
+#[repr(C)]
+pub struct WrenLoadModuleResult {
+	pub source: *const c_char,
+	pub on_complete: Option<WrenLoadModuleCompleteFn>,
+	pub user_data: *mut c_void,
+}
+
 // Loads and returns the source code for the module [name].
-pub type WrenLoadModuleFn = unsafe extern "C" fn(vm: *mut WrenVM, name: *const c_char) -> *mut c_char;
+pub type WrenLoadModuleFn = unsafe extern "C" fn(vm: *mut WrenVM, name: *const c_char) -> WrenLoadModuleResult;
 
 // Returns a pointer to a foreign method on [className] in [module] with
 // [signature].
-pub type WrenBindForeignMethodFn = unsafe extern "C" fn(vm: *mut WrenVM, module: *const c_char, className: *const c_char, isStatic: c_int, signature: *const c_char) -> WrenForeignMethodFn;
+#[allow(non_snake_case)]
+pub type WrenBindForeignMethodFn = unsafe extern "C" fn(vm: *mut WrenVM, module: *const c_char, className: *const c_char, isStatic: bool, signature: *const c_char) -> Option<WrenForeignMethodFn>;
 
 // Displays a string of text to the user.
 pub type WrenWriteFn = unsafe extern "C" fn(vm: *mut WrenVM, text: *const c_char);
@@ -94,17 +133,18 @@ pub struct WrenForeignClassMethods {
 	//
 	// This must be provided. Inside the body of this, it must call
 	// [wrenSetSlotNewForeign()] exactly once.
-    pub allocate: WrenForeignMethodFn,
+    pub allocate: Option<WrenForeignMethodFn>,
 
 	// The callback invoked when the garbage collector is about to collect a
 	// foreign object's memory.
 	//
 	// This may be `NULL` if the foreign class does not need to finalize.
-    pub finalize: WrenFinalizerFn,
+    pub finalize: Option<WrenFinalizerFn>,
 }
 
 // Returns a pair of pointers to the foreign methods used to allocate and
 // finalize the data for instances of [className] in resolved [module].
+#[allow(non_snake_case)]
 pub type WrenBindForeignClassFn = unsafe extern "C" fn(vm: *mut WrenVM, module: *const c_char, className: *const c_char) -> WrenForeignClassMethods;
 
 #[derive(Copy, Clone)]
@@ -113,7 +153,7 @@ pub struct WrenConfiguration {
 	// The callback Wren will use to allocate, reallocate, and deallocate memory.
 	//
 	// If `NULL`, defaults to a built-in function that uses `realloc` and `free`.
-	pub reallocate_fn: WrenReallocateFn,
+	pub reallocate_fn: Option<WrenReallocateFn>,
 
 	// The callback Wren uses to resolve a module name.
 	//
@@ -139,24 +179,25 @@ pub struct WrenConfiguration {
 	// Wren will take ownership of the string you return and free it for you, so
 	// it should be allocated using the same allocation function you provide
 	// above.
-	pub resolve_module_fn: WrenResolveModuleFn,
+	pub resolve_module_fn: Option<WrenResolveModuleFn>,
 
 	// The callback Wren uses to load a module.
 	//
 	// Since Wren does not talk directly to the file system, it relies on the
 	// embedder to physically locate and read the source code for a module. The
 	// first time an import appears, Wren will call this and pass in the name of
-	// the module being imported. The VM should return the soure code for that
-	// module. Memory for the source should be allocated using [reallocateFn] and
-	// Wren will take ownership over it.
+	// the module being imported. The method will return a result, which contains
+	// the source code for that module. Memory for the source is owned by the
+	// host application, and can be freed using the onComplete callback.
 	//
 	// This will only be called once for any given module name. Wren caches the
 	// result internally so subsequent imports of the same module will use the
 	// previous source and not call this.
 	//
 	// If a module with the given name could not be found by the embedder, it
-	// should return NULL and Wren will report that as a runtime error.
-	pub load_module_fn: WrenLoadModuleFn,
+	// should return a result with a NULL source and Wren will report that as a
+	// runtime error.
+	pub load_module_fn: Option<WrenLoadModuleFn>,
 
 	// The callback Wren uses to find a foreign method and bind it to a class.
 	//
@@ -167,7 +208,7 @@ pub struct WrenConfiguration {
 	//
 	// If the foreign function could not be found, this should return NULL and
 	// Wren will report it as runtime error.
-	pub bind_foreign_method_fn: WrenBindForeignMethodFn,
+	pub bind_foreign_method_fn: Option<WrenBindForeignMethodFn>,
 
 	// The callback Wren uses to find a foreign class and get its foreign methods.
 	//
@@ -175,20 +216,20 @@ pub struct WrenConfiguration {
 	// module and name when the class body is executed. It should return the
 	// foreign functions uses to allocate and (optionally) finalize the bytes
 	// stored in the foreign object when an instance is created.
-	pub bind_foreign_class_fn: WrenBindForeignClassFn,
+	pub bind_foreign_class_fn: Option<WrenBindForeignClassFn>,
 
 	// The callback Wren uses to display text when `System.print()` or the other
 	// related functions are called.
 	//
 	// If this is `NULL`, Wren discards any printed text.
-	pub write_fn: WrenWriteFn,
+	pub write_fn: Option<WrenWriteFn>,
 
 	// The callback Wren uses to report errors.
 	//
 	// When an error occurs, this will be called with the module name, line
 	// number, and an error message. If this is `NULL`, Wren doesn't report any
 	// errors.
-	pub error_fn: WrenErrorFn,
+	pub error_fn: Option<WrenErrorFn>,
 
 	// The number of bytes Wren will allocate before triggering the first garbage
 	// collection.
@@ -247,6 +288,7 @@ pub enum WrenType {
     Num,
     Foreign,
     List,
+    Map,
     Null,
     String,
 
@@ -256,6 +298,11 @@ pub enum WrenType {
 
 extern "C" {
 
+// Get the current wren version number.
+//
+// Can be used to range checks over versions.
+pub fn wrenGetVersionNumber() -> c_int;
+
 // Initializes [configuration] with all of its default values.
 //
 // Call this before setting the particular fields you care about.
@@ -358,7 +405,7 @@ pub fn wrenGetSlotType(vm: *mut WrenVM, slot: c_int) -> WrenType;
 // Reads a boolean value from [slot].
 //
 // It is an error to call this if the slot does not contain a boolean value.
-pub fn wrenGetSlotBool(vm: *mut WrenVM, slot: c_int) -> c_int;
+pub fn wrenGetSlotBool(vm: *mut WrenVM, slot: c_int) -> bool;
 
 // Reads a byte array from [slot].
 //
@@ -400,7 +447,7 @@ pub fn wrenGetSlotString(vm: *mut WrenVM, slot: c_int) -> *const c_char;
 pub fn wrenGetSlotHandle(vm: *mut WrenVM, slot: c_int) -> *mut WrenHandle;
 
 // Stores the boolean [value] in [slot].
-pub fn wrenSetSlotBool(vm: *mut WrenVM, slot: c_int, value: c_int);
+pub fn wrenSetSlotBool(vm: *mut WrenVM, slot: c_int, value: bool);
 
 // Stores the array [length] of [bytes] in [slot].
 //
@@ -425,6 +472,9 @@ pub fn wrenSetSlotNewForeign(vm: *mut WrenVM, slot: c_int, classSlot: c_int, siz
 // Stores a new empty list in [slot].
 pub fn wrenSetSlotNewList(vm: *mut WrenVM, slot: c_int);
 
+// Stores a new empty map in [slot].
+pub fn wrenSetSlotNewMap(vm: *mut WrenVM, slot: c_int);
+
 // Stores null in [slot].
 pub fn wrenSetSlotNull(vm: *mut WrenVM, slot: c_int);
 
@@ -448,6 +498,10 @@ pub fn wrenGetListCount(vm: *mut WrenVM, slot: c_int) -> c_int;
 // [elementSlot].
 pub fn wrenGetListElement(vm: *mut WrenVM, listSlot: c_int, index: c_int, elementSlot: c_int);
 
+// Takes the value stored at [elementSlot] and writes it into the list stored
+// at [listSlot] at [index].
+pub fn wrenSetListElement(vm: *mut WrenVM, listSlot: c_int, index: c_int, elementSlot: c_int);
+
 // Takes the value stored at [elementSlot] and inserts it into the list stored
 // at [listSlot] at [index].
 //
@@ -455,10 +509,37 @@ pub fn wrenGetListElement(vm: *mut WrenVM, listSlot: c_int, index: c_int, elemen
 // an element, use `-1` for the index.
 pub fn wrenInsertInList(vm: *mut WrenVM, listSlot: c_int, index: c_int, elementSlot: c_int);
 
+// Returns the number of entries in the map stored in [slot].
+pub fn wrenGetMapCount(vm: *mut WrenVM, slot: c_int) -> c_int;
+
+// Returns true if the key in [keySlot] is found in the map placed in [mapSlot].
+pub fn wrenGetMapContainsKey(vm: *mut WrenVM, mapSlot: c_int, keySlot: c_int) -> bool;
+
+// Retrieves a value with the key in [keySlot] from the map in [mapSlot] and
+// stores it in [valueSlot].
+pub fn wrenGetMapValue(vm: *mut WrenVM, mapSlot: c_int, keySlot: c_int, valueSlot: c_int);
+
+// Takes the value stored in [valueSlot] and inserts it into the map stored
+// at [mapSlot] with key [keySlot].
+pub fn wrenSetMapValue(vm: *mut WrenVM, mapSlot: c_int, keySlot: c_int, valueSlot: c_int);
+
+// Removes a value from the map in [mapSlot], with the key from [keySlot],
+// and place it in [removedValueSlot]. If not found, [removedValueSlot] is
+// set to null, the same behaviour as the Wren Map API.
+pub fn wrenRemoveMapValue(vm: *mut WrenVM, mapSlot: c_int, keySlot: c_int, removedValueSlot: c_int);
+
 // Looks up the top level variable with [name] in resolved [module] and stores
 // it in [slot].
 pub fn wrenGetVariable(vm: *mut WrenVM, module: *const c_char, name: *const c_char, slot: c_int);
 
+// Looks up the top level variable with [name] in resolved [module],
+// returns false if not found. The module must be imported at the time,
+// use wrenHasModule to ensure that before calling.
+pub fn wrenHasVariable(vm: *mut WrenVM, module: *const c_char, name: *const c_char) -> bool;
+
+// Returns true if [module] has been imported/resolved before, false if not.
+pub fn wrenHasModule(vm: *mut WrenVM, module: *const c_char) -> bool;
+
 // Sets the current fiber to be aborted, and uses the value in [slot] as the
 // runtime error object.
 pub fn wrenAbortFiber(vm: *mut WrenVM, slot: c_int);
@@ -470,3 +551,5 @@ pub fn wrenGetUserData(vm: *mut WrenVM) -> *mut c_void;
 pub fn wrenSetUserData(vm: *mut WrenVM, userData: *mut c_void);
 
 }
+
+pub mod wren;
@@ -0,0 +1,39 @@
+//! A safe layer over the raw bindings.
+//!
+//! Everything in here is built on the `extern "C"` declarations at the crate
+//! root, and takes care of the ownership rules `wren.h` leaves to the host.
+
+mod builder;
+mod call;
+#[cfg(feature = "meta")]
+mod check;
+mod class;
+mod context;
+mod convert;
+mod declare;
+mod error;
+mod foreign;
+mod handle;
+mod loader;
+mod reload;
+mod slots;
+mod vm;
+
+pub use self::builder::VmBuilder;
+pub use self::call::Args;
+#[cfg(feature = "meta")]
+pub use self::check::check_syntax;
+pub use self::class::{WrenClass, WrenMethods};
+pub use self::convert::{FromWren, ToWren};
+pub use self::error::{CompileDiagnostic, ErrorEvent, Frame, WrenError};
+pub use self::foreign::{ForeignMethod, ForeignRegistry, ForeignResult, MethodKey};
+pub use self::handle::Handle;
+#[cfg(any(feature = "zip", feature = "tar"))]
+pub use self::loader::ArchiveLoader;
+pub use self::loader::{ChainLoader, FileLoader, MemoryLoader, ModuleLoader};
+pub use self::reload::{ReloadReport, Reloader};
+pub use self::slots::{SlotError, Slots};
+pub use self::vm::Vm;
+
+#[cfg(feature = "derive")]
+pub use wren_derive::{include_wren_dir, wren_methods, WrenClass};
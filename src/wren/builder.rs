@@ -0,0 +1,154 @@
+use std::collections::{HashMap, HashSet};
+use std::mem::MaybeUninit;
+use std::rc::Weak;
+
+use libc::{c_char, c_int};
+
+use super::context::{lossy, Context, ErrorFn, WriteFn};
+use super::class::bind_foreign_class_fn;
+use super::foreign::bind_foreign_method_fn;
+use super::loader::{load_module_fn, resolve_module_fn};
+use super::{ErrorEvent, ForeignRegistry, ModuleLoader, Vm};
+use crate::*;
+
+/// Configures and creates a [`Vm`].
+///
+/// Settings that are left alone keep Wren's defaults. Without a `write`
+/// closure, output goes to stdout.
+pub struct VmBuilder {
+	write: Option<WriteFn>,
+	error: Option<ErrorFn>,
+	foreign: ForeignRegistry,
+	loader: Option<Box<dyn ModuleLoader>>,
+	initial_heap_size: usize,
+	min_heap_size: usize,
+	heap_growth_percent: c_int,
+}
+
+impl VmBuilder {
+	pub fn new() -> Self {
+		VmBuilder {
+			write: None,
+			error: None,
+			foreign: ForeignRegistry::new(),
+			loader: None,
+			initial_heap_size: 0,
+			min_heap_size: 0,
+			heap_growth_percent: 0,
+		}
+	}
+
+	/// Sets the closure called with the text of `System.print()` and friends.
+	pub fn write<F>(mut self, write: F) -> Self
+	where
+		F: FnMut(&str) + 'static,
+	{
+		self.write = Some(Box::new(write));
+		self
+	}
+
+	/// Sets the closure called for each compile error, runtime error and stack
+	/// trace line Wren reports, as it reports them.
+	///
+	/// This is only needed to see errors as they happen; they are also
+	/// returned from [`Vm::interpret`] as a [`WrenError`](super::WrenError).
+	pub fn error<F>(mut self, error: F) -> Self
+	where
+		F: FnMut(ErrorEvent) + 'static,
+	{
+		self.error = Some(Box::new(error));
+		self
+	}
+
+	/// Sets the foreign methods the VM binds when Wren code declares them.
+	/// Importing one of the registry's modules declares them for you.
+	pub fn foreign(mut self, registry: ForeignRegistry) -> Self {
+		self.foreign = registry;
+		self
+	}
+
+	/// Sets where the modules scripts import come from. Without a loader,
+	/// only the registry's modules and Wren's optional ones can be imported.
+	pub fn loader(mut self, loader: impl ModuleLoader + 'static) -> Self {
+		self.loader = Some(Box::new(loader));
+		self
+	}
+
+	/// The number of bytes Wren will allocate before the first garbage
+	/// collection. Defaults to 10MB.
+	pub fn initial_heap_size(mut self, bytes: usize) -> Self {
+		self.initial_heap_size = bytes;
+		self
+	}
+
+	/// The smallest the heap will shrink to after a collection. Defaults to 1MB.
+	pub fn min_heap_size(mut self, bytes: usize) -> Self {
+		self.min_heap_size = bytes;
+		self
+	}
+
+	/// How much the heap grows after a collection, as a percentage of the bytes
+	/// still in use. Defaults to 50.
+	pub fn heap_growth_percent(mut self, percent: u16) -> Self {
+		self.heap_growth_percent = c_int::from(percent);
+		self
+	}
+
+	pub fn build(self) -> Vm {
+		let context = Box::new(Context {
+			write: self.write.unwrap_or_else(|| Box::new(|text| print!("{}", text))),
+			error: self.error.unwrap_or_else(|| Box::new(|_| {})),
+			errors: Default::default(),
+			panic: None,
+			this: Weak::new(),
+			call_handles: HashMap::new(),
+			foreign: self.foreign,
+			bound: Vec::new(),
+			classes: HashSet::new(),
+			loader: self.loader,
+		});
+		let context = Box::into_raw(context);
+
+		unsafe {
+			let mut config = MaybeUninit::<WrenConfiguration>::uninit();
+			wrenInitConfiguration(config.as_mut_ptr());
+			let mut config = config.assume_init();
+
+			config.write_fn = Some(write_fn);
+			config.error_fn = Some(error_fn);
+			config.bind_foreign_method_fn = Some(bind_foreign_method_fn);
+			config.bind_foreign_class_fn = Some(bind_foreign_class_fn);
+			config.resolve_module_fn = Some(resolve_module_fn);
+			config.load_module_fn = Some(load_module_fn);
+			config.initial_heap_size = self.initial_heap_size;
+			config.min_heap_size = self.min_heap_size;
+			config.heap_growth_percent = self.heap_growth_percent;
+			config.user_data = context as *mut _;
+
+			Vm::from_raw(wrenNewVM(&mut config), context)
+		}
+	}
+}
+
+impl Default for VmBuilder {
+	fn default() -> Self {
+		VmBuilder::new()
+	}
+}
+
+unsafe extern "C" fn write_fn(vm: *mut WrenVM, text: *const c_char) {
+	let text = lossy(text).unwrap_or_default();
+	Context::get(vm).catch(|context| (context.write)(&text));
+}
+
+unsafe extern "C" fn error_fn(vm: *mut WrenVM, kind: WrenErrorType, module: *const c_char, line: c_int, message: *const c_char) {
+	let event = ErrorEvent {
+		kind,
+		module: lossy(module),
+		line: if line >= 0 { Some(line as u32) } else { None },
+		message: lossy(message).unwrap_or_default(),
+	};
+	let context = Context::get(vm);
+	context.errors.push(&event);
+	context.catch(|context| (context.error)(event));
+}
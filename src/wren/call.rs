@@ -0,0 +1,231 @@
+use std::ffi::CString;
+
+use super::{FromWren, SlotError, Slots, ToWren, Vm, WrenError};
+use crate::*;
+
+/// The arguments of a method call: `()` or a tuple of [`ToWren`] values.
+pub trait Args {
+	/// The number of arguments.
+	const COUNT: usize;
+
+	/// Stores the arguments in order, starting at slot `first`.
+	fn push(&self, slots: &mut Slots, first: usize) -> Result<(), SlotError>;
+}
+
+impl Args for () {
+	const COUNT: usize = 0;
+
+	fn push(&self, _slots: &mut Slots, _first: usize) -> Result<(), SlotError> {
+		Ok(())
+	}
+}
+
+macro_rules! args {
+	($count:expr; $($name:ident $index:tt),+) => {
+		impl<$($name: ToWren),+> Args for ($($name,)+) {
+			const COUNT: usize = $count;
+
+			fn push(&self, slots: &mut Slots, first: usize) -> Result<(), SlotError> {
+				$(self.$index.to_wren(slots, first + $index)?;)+
+				Ok(())
+			}
+		}
+	};
+}
+
+args!(1; A 0);
+args!(2; A 0, B 1);
+args!(3; A 0, B 1, C 2);
+args!(4; A 0, B 1, C 2, D 3);
+args!(5; A 0, B 1, C 2, D 3, E 4);
+args!(6; A 0, B 1, C 2, D 3, E 4, F 5);
+args!(7; A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+args!(8; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+args!(9; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8);
+args!(10; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9);
+args!(11; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10);
+args!(12; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11);
+args!(13; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12);
+args!(14; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12, N 13);
+args!(15; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12, N 13, O 14);
+args!(16; A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7, I 8, J 9, K 10, L 11, M 12, N 13, O 14, P 15);
+
+// Returns the number of parameters in a method signature, or `None` if it
+// isn't one. Handles methods (`update(_,_)`), getters (`count`), setters
+// (`x=(_)`), operators (`+(_)`, `-`) and subscripts (`[_,_]`, `[_]=(_)`).
+pub(crate) fn signature_arity(signature: &str) -> Option<usize> {
+	if let Some(rest) = signature.strip_prefix('[') {
+		let end = rest.find(']')?;
+		let arity = params(&rest[..end]).filter(|&arity| arity > 0)?;
+		return match &rest[end + 1..] {
+			"" => Some(arity),
+			"=(_)" => Some(arity + 1),
+			_ => None,
+		};
+	}
+
+	let starts_name = signature.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_');
+	if !starts_name {
+		let name_len = signature.find('(').unwrap_or(signature.len());
+		let (name, rest) = signature.split_at(name_len);
+		return match rest {
+			"" if PREFIX_OPERATORS.contains(&name) => Some(0),
+			"(_)" if INFIX_OPERATORS.contains(&name) => Some(1),
+			_ => None,
+		};
+	}
+
+	let name_len = signature
+		.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+		.unwrap_or(signature.len());
+	match &signature[name_len..] {
+		"" => Some(0),
+		"=(_)" => Some(1),
+		rest => params(rest.strip_prefix('(')?.strip_suffix(')')?),
+	}
+}
+
+const PREFIX_OPERATORS: &[&str] = &["-", "!", "~"];
+const INFIX_OPERATORS: &[&str] = &[
+	"+", "-", "*", "/", "%", "<", ">", "<=", ">=", "==", "!=", "&", "|", "^", "<<", ">>", "..", "...",
+];
+
+// Counts the `_`s in a comma separated parameter list.
+fn params(list: &str) -> Option<usize> {
+	if list.is_empty() {
+		return Some(0);
+	}
+	list.split(',').map(|param| if param == "_" { Some(1) } else { None }).sum()
+}
+
+impl Vm {
+	/// Calls the method with `signature` on `receiver`, and converts what it
+	/// returns to a `T`.
+	///
+	/// The receiver is usually a [`Handle`](super::Handle) to an object, or to
+	/// a class for static methods. The arguments are a tuple whose length
+	/// must match the number of parameters in `signature`:
+	///
+	/// ```
+	/// # use wren_sys::wren::{Handle, Vm};
+	/// # let mut vm = Vm::new();
+	/// # vm.interpret("main", "class Game {\n\tstatic update(dt, key) { dt * 2 }\n}")?;
+	/// # let game: Handle = vm.get_variable("main", "Game")?;
+	/// # let dt = 0.5;
+	/// let next: f64 = vm.call(&game, "update(_,_)", (dt, "left"))?;
+	/// # assert_eq!(next, 1.0);
+	/// # Ok::<(), wren_sys::wren::WrenError>(())
+	/// ```
+	///
+	/// Call handles are created once per signature and reused.
+	pub fn call<T, R, A>(&mut self, receiver: R, signature: &str, args: A) -> Result<T, WrenError>
+	where
+		T: FromWren,
+		R: ToWren,
+		A: Args,
+	{
+		let arity = signature_arity(signature).ok_or_else(|| WrenError::Signature(signature.to_string()))?;
+		if arity != A::COUNT {
+			return Err(WrenError::Arity {
+				signature: signature.to_string(),
+				arity,
+				args: A::COUNT,
+			});
+		}
+		let method = self.call_handle(signature)?;
+
+		let mut slots = self.slots();
+		slots.ensure(arity + 1)?;
+		receiver.to_wren(&mut slots, 0)?;
+		args.push(&mut slots, 1)?;
+
+		let result = unsafe { wrenCall(self.as_ptr(), method) };
+
+		let context = self.context();
+		let result = context.errors.finish(result);
+		context.resume_panic();
+		result?;
+
+		Ok(self.slots().get(0)?)
+	}
+
+	// Returns the cached call handle for [signature], creating it if needed.
+	fn call_handle(&mut self, signature: &str) -> Result<*mut WrenHandle, WrenError> {
+		if let Some(&handle) = self.context().call_handles.get(signature) {
+			return Ok(handle);
+		}
+
+		let c_signature = CString::new(signature)?;
+		let handle = unsafe { wrenMakeCallHandle(self.as_ptr(), c_signature.as_ptr()) };
+		self.context().call_handles.insert(signature.to_string(), handle);
+		Ok(handle)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::signature_arity;
+
+	#[test]
+	fn methods() {
+		assert_eq!(signature_arity("update()"), Some(0));
+		assert_eq!(signature_arity("update(_)"), Some(1));
+		assert_eq!(signature_arity("update(_,_)"), Some(2));
+		assert_eq!(signature_arity("call(_,_,_,_)"), Some(4));
+		assert_eq!(signature_arity("is_empty_2(_)"), Some(1));
+	}
+
+	#[test]
+	fn getters_and_setters() {
+		assert_eq!(signature_arity("count"), Some(0));
+		assert_eq!(signature_arity("_private"), Some(0));
+		assert_eq!(signature_arity("x=(_)"), Some(1));
+	}
+
+	#[test]
+	fn operators() {
+		assert_eq!(signature_arity("+(_)"), Some(1));
+		assert_eq!(signature_arity("==(_)"), Some(1));
+		assert_eq!(signature_arity("..(_)"), Some(1));
+		assert_eq!(signature_arity("-"), Some(0));
+		assert_eq!(signature_arity("!"), Some(0));
+		assert_eq!(signature_arity("-(_)"), Some(1));
+	}
+
+	#[test]
+	fn subscripts() {
+		assert_eq!(signature_arity("[_]"), Some(1));
+		assert_eq!(signature_arity("[_,_]"), Some(2));
+		assert_eq!(signature_arity("[_]=(_)"), Some(2));
+		assert_eq!(signature_arity("[_,_]=(_)"), Some(3));
+	}
+
+	#[test]
+	fn malformed() {
+		for signature in &[
+			"",
+			"foo bar",
+			"foo (_)",
+			"f(_)(_)",
+			"f(_",
+			"f(x)",
+			"f(_,)",
+			"f(,_)",
+			"x=(_,_)",
+			"x=()",
+			"x=",
+			"[]",
+			"[_]=(_,_)",
+			"[_](_)",
+			"1foo",
+			"+",
+			"*",
+			"!(_)",
+			"+(_,_)",
+			"=(_)",
+			"(_)",
+		] {
+			assert_eq!(signature_arity(signature), None, "{:?}", signature);
+		}
+	}
+}
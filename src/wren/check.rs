@@ -0,0 +1,117 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use super::{CompileDiagnostic, Handle, ModuleLoader, VmBuilder, WrenError};
+use crate::WrenErrorType;
+
+// `Meta.compile` compiles into the module of the function calling it. This is
+// the whole of the checked module until then: imported by the driver from a
+// fiber, it hands the driver such a function and stops, without declaring
+// any variables the checked source could clash with.
+const COMPILER: &str = "Fiber.yield(Fn.new {|meta, source| meta.compile(source) })";
+
+// Serves `COMPILER` as the checked module.
+struct Checked(String);
+
+impl ModuleLoader for Checked {
+	fn load(&mut self, name: &str) -> Option<Cow<'_, str>> {
+		if name == self.0 {
+			Some(Cow::Borrowed(COMPILER))
+		} else {
+			None
+		}
+	}
+}
+
+/// Compiles `source` as the module `module` without running it, in a VM of
+/// its own, and returns the compile errors if there are any.
+///
+/// Imports aren't followed, so names imported from other modules aren't
+/// checked:
+///
+/// ```
+/// # use wren_sys::wren::{check_syntax, WrenError};
+/// match check_syntax("main", "import \"missing\"\nSystem.print(\"unclosed\"") {
+///     Err(WrenError::Compile(diagnostics)) => assert_eq!(diagnostics[0].line, 2),
+///     other => panic!("{:?}", other),
+/// }
+/// ```
+///
+/// This is meant for build scripts, to catch syntax errors in the scripts a
+/// binary embeds before shipping it:
+///
+/// ```
+/// # #[cfg(feature = "derive")] {
+/// // build.rs, with wren-sys (and its `derive` feature) as a build dependency
+/// use wren_sys::wren::{check_syntax, include_wren_dir};
+///
+/// let scripts = include_wren_dir!("tests/fixtures/scripts");
+/// for (module, source) in scripts.iter() {
+///     if let Err(err) = check_syntax(module, source) {
+///         panic!("{}", err);
+///     }
+/// }
+/// # }
+/// ```
+pub fn check_syntax(module: &str, source: &str) -> Result<(), WrenError> {
+	let diagnostics = Rc::new(RefCell::new(Vec::new()));
+	let collected = diagnostics.clone();
+	let mut vm = VmBuilder::new()
+		.write(|_| {})
+		.error(move |event| {
+			if event.kind == WrenErrorType::Compile {
+				collected.borrow_mut().push(CompileDiagnostic {
+					module: event.module.unwrap_or_default(),
+					line: event.line.unwrap_or(0),
+					message: event.message,
+				});
+			}
+		})
+		.loader(Checked(module.to_string()))
+		.build();
+
+	let driver = if module == "wren_sys_check" { "wren_sys_check_driver" } else { "wren_sys_check" };
+	let driver_source = format!(
+		"import \"meta\" for Meta\nvar Compiler = Fiber.new {{ import \"{}\" }}.call()",
+		escape(module)
+	);
+	vm.interpret(driver, &driver_source)?;
+	let meta: Handle = vm.get_variable(driver, "Meta")?;
+	let compiler: Handle = vm.get_variable(driver, "Compiler")?;
+
+	let compiled: Option<Handle> = vm.call(&compiler, "call(_,_)", (&meta, source))?;
+	match compiled {
+		Some(_) => Ok(()),
+		None => Err(WrenError::Compile(diagnostics.take())),
+	}
+}
+
+// Escapes [text] for use inside a Wren string literal.
+fn escape(text: &str) -> String {
+	let mut escaped = String::with_capacity(text.len());
+	for c in text.chars() {
+		match c {
+			'"' | '\\' | '%' => {
+				escaped.push('\\');
+				escaped.push(c);
+			}
+			'\n' => escaped.push_str("\\n"),
+			'\r' => escaped.push_str("\\r"),
+			'\t' => escaped.push_str("\\t"),
+			c => escaped.push(c),
+		}
+	}
+	escaped
+}
+
+#[cfg(test)]
+mod tests {
+	use super::escape;
+
+	#[test]
+	fn escapes_string_literals() {
+		assert_eq!(escape("lib/vector"), "lib/vector");
+		assert_eq!(escape("a\"b\\c%(d)\n"), "a\\\"b\\\\c\\%(d)\\n");
+	}
+}
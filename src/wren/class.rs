@@ -0,0 +1,276 @@
+use std::any::{type_name, TypeId};
+use std::ffi::CString;
+use std::mem::{align_of, size_of};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::rc::Rc;
+
+use libc::{c_char, c_int, c_void};
+
+use super::context::{lossy, Context};
+use super::foreign::{bind, MethodKey};
+use super::{ForeignRegistry, FromWren, SlotError, Slots};
+use crate::*;
+
+// Marks foreign data laid out by this module, so that objects allocated some
+// other way (like `Random` from Wren's optional module) are not mistaken for
+// one of ours.
+const MAGIC: u64 = 0x7772_656e_2d73_7973;
+
+// What constructing a foreign class that couldn't be bound aborts the fiber
+// with.
+pub(crate) const EXHAUSTED_CLASS: &str = "Could not bind foreign class allocator: too many foreign methods.";
+const UNREGISTERED_CLASS: &str = "No allocator registered for foreign class.";
+
+// Written at the start of the storage of every foreign object allocated here.
+// Wren only guarantees the storage is pointer aligned, so the header is read
+// and written unaligned, and the value is placed after it at whatever offset
+// its own alignment needs.
+#[derive(Clone, Copy)]
+#[repr(C)]
+struct Header {
+	magic: u64,
+	type_id: TypeId,
+	type_name: &'static str,
+	offset: usize,
+	drop: unsafe fn(*mut u8),
+}
+
+unsafe fn drop_value<T>(value: *mut u8) {
+	ptr::drop_in_place(value as *mut T);
+}
+
+impl<'vm> Slots<'vm> {
+	/// Creates an instance of the foreign class in `class_slot` holding
+	/// `value`, and stores it in `slot`. The value is dropped when Wren
+	/// garbage collects the object.
+	///
+	/// This doesn't run any Wren constructor. Inside an allocator registered
+	/// with [`ForeignRegistry::class`] this is done for you, and elsewhere
+	/// [`Slots::set_foreign`] checks the class first.
+	///
+	/// # Safety
+	///
+	/// `class_slot` must hold a foreign class registered with
+	/// [`ForeignRegistry::class`]. Wren only checks that it is a foreign class
+	/// at all in debug builds, and the methods of foreign classes from
+	/// elsewhere, like `Random`, expect data of their own.
+	pub unsafe fn new_foreign<T: 'static>(&mut self, slot: usize, class_slot: usize, value: T) -> Result<&mut T, SlotError> {
+		self.slot_type(slot)?;
+		// Classes have no `WrenType` of their own.
+		match self.slot_type(class_slot)? {
+			WrenType::Unknown => {}
+			found => return Err(SlotError::Type { slot: class_slot, expected: WrenType::Unknown, found }),
+		}
+
+		let header_size = size_of::<Header>();
+		let size = header_size + align_of::<T>() - 1 + size_of::<T>();
+
+		unsafe {
+			let data = wrenSetSlotNewForeign(self.as_ptr(), slot as c_int, class_slot as c_int, size) as *mut u8;
+			let start = data as usize + header_size;
+			let offset = header_size + (start.wrapping_neg() & (align_of::<T>() - 1));
+
+			let header = Header {
+				magic: MAGIC,
+				type_id: TypeId::of::<T>(),
+				type_name: type_name::<T>(),
+				offset,
+				drop: drop_value::<T>,
+			};
+			ptr::write_unaligned(data as *mut Header, header);
+
+			let value_ptr = data.add(offset) as *mut T;
+			ptr::write(value_ptr, value);
+			Ok(&mut *value_ptr)
+		}
+	}
+
+	/// Creates an instance of the foreign class `class` from the resolved
+	/// `module` holding `value`, and stores it in `slot`. Useful for returning
+	/// new objects from foreign methods.
+	///
+	/// The class must be registered with [`ForeignRegistry::class`], and
+	/// declared and bound by the VM.
+	pub fn set_foreign<T: 'static>(&mut self, slot: usize, module: &str, class: &str, value: T) -> Result<(), SlotError> {
+		let unknown = || SlotError::UnknownClass {
+			module: module.to_string(),
+			class: class.to_string(),
+		};
+		let key = (module.to_string(), class.to_string());
+		if !unsafe { Context::get(self.as_ptr()) }.classes.contains(&key) {
+			return Err(unknown());
+		}
+		let c_module = CString::new(module).map_err(|_| unknown())?;
+		let c_class = CString::new(class).map_err(|_| unknown())?;
+
+		let class_slot = self.scratch(1)?;
+		unsafe {
+			let vm = self.as_ptr();
+			// Wren asserts both exist before looking the variable up.
+			if !wrenHasModule(vm, c_module.as_ptr()) || !wrenHasVariable(vm, c_module.as_ptr(), c_class.as_ptr()) {
+				return Err(unknown());
+			}
+			wrenGetVariable(vm, c_module.as_ptr(), c_class.as_ptr(), class_slot as c_int);
+			// The variable holds the class Wren bound through the registry,
+			// as declared.
+			self.new_foreign(slot, class_slot, value).map(|_| ())
+		}
+	}
+
+	/// Returns the Rust value inside the foreign object in `slot`, checking
+	/// that it is a `T`.
+	///
+	/// Only one value can be borrowed at a time, so read any other arguments
+	/// before borrowing the receiver.
+	pub fn get_foreign<T: 'static>(&mut self, slot: usize) -> Result<&mut T, SlotError> {
+		let data = self.get_foreign_ptr(slot)? as *mut u8;
+		unsafe {
+			let header = ptr::read_unaligned(data as *const Header);
+			if header.magic != MAGIC || header.type_id != TypeId::of::<T>() {
+				return Err(SlotError::ForeignType {
+					slot,
+					expected: type_name::<T>(),
+					found: if header.magic == MAGIC { Some(header.type_name) } else { None },
+				});
+			}
+			Ok(&mut *(data.add(header.offset) as *mut T))
+		}
+	}
+}
+
+/// A Rust type exposed to Wren as a foreign class, usually with
+/// `#[derive(WrenClass)]`.
+pub trait WrenClass: 'static {
+	/// The resolved module the class is declared in.
+	const MODULE: &'static str;
+	/// The name of the class in Wren.
+	const NAME: &'static str;
+}
+
+/// The constructors and foreign methods of a [`WrenClass`], usually
+/// generated by `#[wren_methods]`.
+pub trait WrenMethods: WrenClass {
+	/// Registers the class's allocator and all of its methods.
+	fn register(registry: &mut ForeignRegistry);
+
+	/// The `foreign class` declaration matching what `register` binds, for
+	/// scripts declaring the class themselves.
+	fn source() -> String {
+		let mut registry = ForeignRegistry::new();
+		Self::register(&mut registry);
+		registry.class_source(Self::MODULE, Self::NAME)
+	}
+}
+
+// Foreign objects are owned by Wren, so reading one by value takes a copy.
+// Borrow it in place with `Slots::get_foreign` instead.
+impl<T: WrenClass + Clone> FromWren for T {
+	fn from_wren(slots: &mut Slots, slot: usize) -> Result<Self, SlotError> {
+		slots.get_foreign::<T>(slot).map(|value| value.clone())
+	}
+}
+
+impl ForeignRegistry {
+	/// Registers the foreign class `T` and its methods.
+	pub fn register<T: WrenMethods>(&mut self) -> &mut Self {
+		T::register(self);
+		self
+	}
+
+	/// Registers `class` in the resolved `module` as a foreign class holding a
+	/// `T`.
+	///
+	/// `constructor` builds the value when Wren creates an instance, from the
+	/// arguments of whichever Wren constructor was called (slots 1 and up).
+	/// The value is dropped when the object is garbage collected. Methods get
+	/// at it with [`Slots::get_foreign`].
+	///
+	/// The value must not hold a [`Handle`](super::Handle), directly or not.
+	/// The handle would keep the VM alive, and with it the object holding the
+	/// handle, so neither is ever freed. Wren also doesn't allow releasing a
+	/// handle while it's finalizing an object.
+	pub fn class<T, F>(&mut self, module: &str, class: &str, constructor: F) -> &mut Self
+	where
+		T: 'static,
+		F: Fn(&mut Slots) -> Result<T, Box<dyn std::error::Error>> + 'static,
+	{
+		let allocate = move |slots: &mut Slots| {
+			let value = constructor(slots)?;
+			// Inside an allocator the class is in slot 0, and it's this one.
+			unsafe { slots.new_foreign(0, 0, value)? };
+			Ok(())
+		};
+		self.classes.insert((module.to_string(), class.to_string()), Rc::new(allocate));
+		self
+	}
+}
+
+unsafe extern "C" fn finalize(data: *mut c_void) {
+	let data = data as *mut u8;
+	let header = ptr::read_unaligned(data as *const Header);
+	if header.magic == MAGIC {
+		// Wren is in the middle of collecting garbage and has no way to report
+		// an error, so a panicking `Drop` is only left to the panic hook to
+		// print.
+		let _ = panic::catch_unwind(AssertUnwindSafe(|| (header.drop)(data.add(header.offset))));
+	}
+}
+
+// Stand in for the allocator of a foreign class that couldn't be bound, so
+// that constructing one aborts the fiber instead of calling a null pointer.
+unsafe extern "C" fn exhausted_allocate(vm: *mut WrenVM) {
+	abort_allocate(vm, EXHAUSTED_CLASS);
+}
+
+unsafe extern "C" fn unregistered_allocate(vm: *mut WrenVM) {
+	abort_allocate(vm, UNREGISTERED_CLASS);
+}
+
+unsafe fn abort_allocate(vm: *mut WrenVM, message: &str) {
+	let mut slots = Slots::from_raw(vm);
+	let _ = slots.set_str(0, message);
+	let _ = slots.abort_fiber(0);
+}
+
+pub(crate) unsafe extern "C" fn bind_foreign_class_fn(
+	vm: *mut WrenVM,
+	module: *const c_char,
+	class_name: *const c_char,
+) -> WrenForeignClassMethods {
+	let context = Context::get(vm);
+	let key = (lossy(module).unwrap_or_default(), lossy(class_name).unwrap_or_default());
+
+	let allocate = match context.foreign.classes.get(&key).cloned() {
+		Some(allocate) => allocate,
+		// Leave `Random` to Wren, which only binds it if nothing else did.
+		None if cfg!(feature = "random") && key.0 == "random" => {
+			return WrenForeignClassMethods { allocate: None, finalize: None };
+		}
+		None => {
+			return WrenForeignClassMethods {
+				allocate: Some(unregistered_allocate),
+				finalize: None,
+			};
+		}
+	};
+
+	match bind(context, allocate) {
+		Some(allocate) => {
+			context.classes.insert(key);
+			WrenForeignClassMethods {
+				allocate: Some(allocate),
+				finalize: Some(finalize),
+			}
+		}
+		None => {
+			let (module, class) = key;
+			let signature = "<allocate>".to_string();
+			context.errors.unbound(MethodKey { module, class, is_static: true, signature }, true);
+			WrenForeignClassMethods {
+				allocate: Some(exhausted_allocate),
+				finalize: None,
+			}
+		}
+	}
+}
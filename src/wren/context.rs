@@ -0,0 +1,83 @@
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::ffi::CStr;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Weak;
+
+use libc::c_char;
+
+use super::error::ErrorCollector;
+use super::foreign::{ForeignMethod, ForeignRegistry};
+use super::loader::ModuleLoader;
+use super::vm::Raw;
+use super::ErrorEvent;
+use crate::*;
+
+pub(crate) type WriteFn = Box<dyn FnMut(&str)>;
+pub(crate) type ErrorFn = Box<dyn FnMut(ErrorEvent)>;
+
+// The Rust side of a VM, stored behind its `user_data` pointer so the
+// `extern "C"` callbacks can get back to it.
+pub(crate) struct Context {
+	pub write: WriteFn,
+	pub error: ErrorFn,
+	pub errors: ErrorCollector,
+
+	// A panic caught in a callback, resumed once control is back in Rust.
+	pub panic: Option<Box<dyn Any + Send + 'static>>,
+
+	// The VM this is the context of, for creating handles from slots.
+	pub this: Weak<Raw>,
+
+	// Call handles made by `Vm::call`, by signature. These are released when
+	// the VM is freed.
+	pub call_handles: HashMap<String, *mut WrenHandle>,
+
+	pub foreign: ForeignRegistry,
+
+	// The foreign methods bound so far, indexed by shim.
+	pub bound: Vec<ForeignMethod>,
+
+	// The foreign classes bound with an allocator from `foreign`, by module
+	// and class name.
+	pub classes: HashSet<(String, String)>,
+
+	pub loader: Option<Box<dyn ModuleLoader>>,
+}
+
+impl Context {
+	// Returns the context of [vm].
+	//
+	// The VM must have been created by `VmBuilder::build`, and the reference
+	// must not outlive the callback it is used in.
+	pub unsafe fn get<'a>(vm: *mut WrenVM) -> &'a mut Context {
+		&mut *(wrenGetUserData(vm) as *mut Context)
+	}
+
+	// Runs [f], catching any panic so it doesn't unwind into Wren.
+	pub fn catch<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> Option<R> {
+		match panic::catch_unwind(AssertUnwindSafe(|| f(self))) {
+			Ok(value) => Some(value),
+			Err(payload) => {
+				self.panic.get_or_insert(payload);
+				None
+			}
+		}
+	}
+
+	// Resumes a panic caught while Wren was running, if there was one.
+	pub fn resume_panic(&mut self) {
+		if let Some(payload) = self.panic.take() {
+			panic::resume_unwind(payload);
+		}
+	}
+}
+
+// Reads a string handed to us by Wren, which may be NULL.
+pub(crate) unsafe fn lossy(text: *const c_char) -> Option<String> {
+	if text.is_null() {
+		None
+	} else {
+		Some(CStr::from_ptr(text).to_string_lossy().into_owned())
+	}
+}
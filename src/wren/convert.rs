@@ -0,0 +1,270 @@
+use std::collections::{BTreeMap, HashMap};
+use std::convert::TryFrom;
+use std::hash::{BuildHasher, Hash};
+
+use super::{Handle, SlotError, Slots};
+use crate::WrenType;
+
+/// A Rust type that can be read out of a slot.
+pub trait FromWren: Sized {
+	fn from_wren(slots: &mut Slots, slot: usize) -> Result<Self, SlotError>;
+}
+
+/// A Rust type that can be stored in a slot.
+pub trait ToWren {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError>;
+}
+
+impl<'vm> Slots<'vm> {
+	/// Reads the value in `slot` as a `T`.
+	pub fn get<T: FromWren>(&mut self, slot: usize) -> Result<T, SlotError> {
+		T::from_wren(self, slot)
+	}
+
+	/// Stores `value` in `slot`.
+	pub fn set<T: ToWren>(&mut self, slot: usize, value: T) -> Result<(), SlotError> {
+		value.to_wren(self, slot)
+	}
+
+	/// Looks up `key` in the map in `map_slot`. Wren's API can't list the keys
+	/// of a map, so maps can only be read from one key at a time.
+	pub fn map_get<K: ToWren, V: FromWren>(&mut self, map_slot: usize, key: K) -> Result<Option<V>, SlotError> {
+		let key_slot = self.scratch(2)?;
+		key.to_wren(self, key_slot)?;
+
+		if !self.map_contains_key(map_slot, key_slot)? {
+			return Ok(None);
+		}
+		self.get_map_value(map_slot, key_slot, key_slot + 1)?;
+		V::from_wren(self, key_slot + 1).map(Some)
+	}
+
+	// Adds [count] slots past the ones in use and returns the first of them.
+	pub(crate) fn scratch(&mut self, count: usize) -> Result<usize, SlotError> {
+		let first = self.count();
+		self.ensure(first + count)?;
+		Ok(first)
+	}
+}
+
+impl FromWren for bool {
+	fn from_wren(slots: &mut Slots, slot: usize) -> Result<Self, SlotError> {
+		slots.get_bool(slot)
+	}
+}
+
+impl ToWren for bool {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		slots.set_bool(slot, *self)
+	}
+}
+
+impl FromWren for f64 {
+	fn from_wren(slots: &mut Slots, slot: usize) -> Result<Self, SlotError> {
+		slots.get_double(slot)
+	}
+}
+
+impl ToWren for f64 {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		slots.set_double(slot, *self)
+	}
+}
+
+impl FromWren for f32 {
+	fn from_wren(slots: &mut Slots, slot: usize) -> Result<Self, SlotError> {
+		slots.get_double(slot).map(|value| value as f32)
+	}
+}
+
+impl ToWren for f32 {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		slots.set_double(slot, f64::from(*self))
+	}
+}
+
+// Wren numbers are doubles, so integers are only converted when the double
+// holds a whole number in range, and back when the integer fits in a double
+// exactly. `u8` is left out so that `Vec<u8>` and `[u8]` can mean byte
+// strings.
+macro_rules! integer {
+	($($ty:ty),*) => {$(
+		impl FromWren for $ty {
+			fn from_wren(slots: &mut Slots, slot: usize) -> Result<Self, SlotError> {
+				let value = slots.get_double(slot)?;
+				// Every integer type fits in an i128, so going through one
+				// catches out of range values instead of saturating.
+				let wide = value as i128;
+				match <$ty>::try_from(wide) {
+					Ok(int) if value.fract() == 0.0 && wide as f64 == value => Ok(int),
+					_ => Err(SlotError::Integer { slot, value, target: stringify!($ty) }),
+				}
+			}
+		}
+
+		impl ToWren for $ty {
+			fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+				let wide = *self as i128;
+				let value = wide as f64;
+				if value as i128 == wide {
+					slots.set_double(slot, value)
+				} else {
+					Err(SlotError::Integer { slot, value, target: "f64" })
+				}
+			}
+		}
+	)*};
+}
+
+integer!(i8, i16, i32, i64, isize, u16, u32, u64, usize);
+
+impl FromWren for String {
+	fn from_wren(slots: &mut Slots, slot: usize) -> Result<Self, SlotError> {
+		slots.get_str(slot).map(String::from)
+	}
+}
+
+impl ToWren for str {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		slots.set_str(slot, self)
+	}
+}
+
+impl ToWren for String {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		slots.set_str(slot, self)
+	}
+}
+
+impl FromWren for Vec<u8> {
+	fn from_wren(slots: &mut Slots, slot: usize) -> Result<Self, SlotError> {
+		slots.get_bytes(slot).map(Vec::from)
+	}
+}
+
+impl ToWren for [u8] {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		slots.set_bytes(slot, self)
+	}
+}
+
+impl ToWren for Vec<u8> {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		slots.set_bytes(slot, self)
+	}
+}
+
+/// Reads any value, discarding it. Stores null.
+impl FromWren for () {
+	fn from_wren(slots: &mut Slots, slot: usize) -> Result<Self, SlotError> {
+		slots.slot_type(slot).map(|_| ())
+	}
+}
+
+impl ToWren for () {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		slots.set_null(slot)
+	}
+}
+
+impl FromWren for Handle {
+	fn from_wren(slots: &mut Slots, slot: usize) -> Result<Self, SlotError> {
+		slots.get_handle(slot)
+	}
+}
+
+impl ToWren for Handle {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		slots.set_handle(slot, self)
+	}
+}
+
+/// `None` is null.
+impl<T: FromWren> FromWren for Option<T> {
+	fn from_wren(slots: &mut Slots, slot: usize) -> Result<Self, SlotError> {
+		match slots.slot_type(slot)? {
+			WrenType::Null => Ok(None),
+			_ => T::from_wren(slots, slot).map(Some),
+		}
+	}
+}
+
+impl<T: ToWren> ToWren for Option<T> {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		match self {
+			Some(value) => value.to_wren(slots, slot),
+			None => slots.set_null(slot),
+		}
+	}
+}
+
+impl<T: ToWren + ?Sized> ToWren for &T {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		(**self).to_wren(slots, slot)
+	}
+}
+
+impl<T: FromWren> FromWren for Vec<T> {
+	fn from_wren(slots: &mut Slots, slot: usize) -> Result<Self, SlotError> {
+		let count = slots.list_count(slot)?;
+		let element = slots.scratch(1)?;
+
+		let mut list = Vec::with_capacity(count);
+		for index in 0..count {
+			slots.get_list_element(slot, index as isize, element)?;
+			list.push(T::from_wren(slots, element)?);
+		}
+		Ok(list)
+	}
+}
+
+impl<T: ToWren> ToWren for [T] {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		slots.set_new_list(slot)?;
+		let element = slots.scratch(1)?;
+
+		for value in self {
+			value.to_wren(slots, element)?;
+			slots.insert_in_list(slot, -1, element)?;
+		}
+		Ok(())
+	}
+}
+
+impl<T: ToWren> ToWren for Vec<T> {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		self.as_slice().to_wren(slots, slot)
+	}
+}
+
+// Stores the entries of a Rust map in a new Wren map in [slot].
+fn map_to_wren<'a, K, V, I>(slots: &mut Slots, slot: usize, entries: I) -> Result<(), SlotError>
+where
+	K: ToWren + 'a,
+	V: ToWren + 'a,
+	I: IntoIterator<Item = (&'a K, &'a V)>,
+{
+	slots.set_new_map(slot)?;
+	let key = slots.scratch(2)?;
+
+	for (k, v) in entries {
+		k.to_wren(slots, key)?;
+		v.to_wren(slots, key + 1)?;
+		slots.set_map_value(slot, key, key + 1)?;
+	}
+	Ok(())
+}
+
+/// Only stores maps: Wren's API can't list the keys of a map, so there is no
+/// `FromWren`. Use [`Slots::map_get`] to read one.
+impl<K: ToWren + Eq + Hash, V: ToWren, S: BuildHasher> ToWren for HashMap<K, V, S> {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		map_to_wren(slots, slot, self)
+	}
+}
+
+impl<K: ToWren + Ord, V: ToWren> ToWren for BTreeMap<K, V> {
+	fn to_wren(&self, slots: &mut Slots, slot: usize) -> Result<(), SlotError> {
+		map_to_wren(slots, slot, self)
+	}
+}
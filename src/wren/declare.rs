@@ -0,0 +1,224 @@
+// Writes Wren declarations for what a `ForeignRegistry` binds, so that scripts
+// don't have to repeat them by hand.
+
+use std::borrow::Cow;
+use std::collections::BTreeSet;
+
+use super::{ForeignRegistry, ModuleLoader};
+
+impl ForeignRegistry {
+	/// Declares a constructor of the foreign class `class` in the resolved
+	/// `module`, e.g. `"new(_,_)"`.
+	///
+	/// This is only used for the class's declaration: Wren runs the allocator
+	/// registered with [`ForeignRegistry::class`] for every constructor.
+	pub fn constructor(&mut self, module: &str, class: &str, signature: &str) -> &mut Self {
+		let constructors = self.constructors.entry((module.to_string(), class.to_string())).or_default();
+		if !constructors.iter().any(|existing| existing == signature) {
+			constructors.push(signature.to_string());
+		}
+		self
+	}
+
+	/// Writes the declarations of every class with something registered in
+	/// the resolved `module`, or `None` if there are none.
+	///
+	/// Classes registered with [`ForeignRegistry::class`] are declared as
+	/// `foreign class`, along with their constructors, and the rest as plain
+	/// classes. Methods get placeholder parameter names:
+	///
+	/// ```text
+	/// foreign class Vec3 {
+	///     construct new(a0, a1, a2) {}
+	///     foreign +(a0)
+	/// }
+	/// ```
+	///
+	/// The VM loads this when a script imports `module`, so a module the
+	/// registry declares can't be the one passed to [`Vm::interpret`](super::Vm::interpret).
+	pub fn source(&self, module: &str) -> Option<String> {
+		let classes = self.classes_in(module);
+		if classes.is_empty() {
+			return None;
+		}
+
+		let declarations: Vec<String> = classes.iter().map(|class| self.class_source(module, class)).collect();
+		Some(declarations.join("\n"))
+	}
+
+	fn classes_in(&self, module: &str) -> BTreeSet<&str> {
+		self.methods
+			.keys()
+			.map(|key| (key.module.as_str(), key.class.as_str()))
+			.chain(self.classes.keys().map(|(module, class)| (module.as_str(), class.as_str())))
+			.filter(|(class_module, _)| *class_module == module)
+			.map(|(_, class)| class)
+			.collect()
+	}
+
+	pub(crate) fn class_source(&self, module: &str, class: &str) -> String {
+		let key = (module.to_string(), class.to_string());
+		let mut methods: Vec<_> = self
+			.methods
+			.keys()
+			.filter(|key| key.module == module && key.class == class)
+			.map(|key| (key.is_static, key.signature.as_str()))
+			.collect();
+		methods.sort();
+
+		let mut source = if self.classes.contains_key(&key) {
+			format!("foreign class {} {{\n", class)
+		} else {
+			format!("class {} {{\n", class)
+		};
+		for signature in self.constructors.get(&key).into_iter().flatten() {
+			source.push_str(&format!("\tconstruct {} {{}}\n", declaration(signature)));
+		}
+		for (is_static, signature) in methods {
+			let keyword = if is_static { "static " } else { "" };
+			source.push_str(&format!("\tforeign {}{}\n", keyword, declaration(signature)));
+		}
+		source.push_str("}\n");
+		source
+	}
+}
+
+// Turns a signature like `[_,_]=(_)` into a declaration like `[a0, a1]=(a2)`.
+fn declaration(signature: &str) -> String {
+	let mut declaration = String::with_capacity(signature.len() * 2);
+	let mut depth = 0;
+	let mut params = 0;
+	for c in signature.chars() {
+		match c {
+			'(' | '[' => depth += 1,
+			')' | ']' => depth -= 1,
+			_ => {}
+		}
+		match c {
+			// Method names can contain underscores too.
+			'_' if depth > 0 => {
+				declaration.push_str(&format!("a{}", params));
+				params += 1;
+			}
+			',' => declaration.push_str(", "),
+			_ => declaration.push(c),
+		}
+	}
+	declaration
+}
+
+// The registry's modules are served to the VM before its own loader is asked.
+impl ModuleLoader for ForeignRegistry {
+	fn resolve(&mut self, _importer: &str, name: &str) -> Option<String> {
+		if self.classes_in(name).is_empty() {
+			None
+		} else {
+			Some(name.to_string())
+		}
+	}
+
+	fn load(&mut self, name: &str) -> Option<Cow<'_, str>> {
+		self.source(name).map(Cow::Owned)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::wren::call::signature_arity;
+
+	fn registry() -> ForeignRegistry {
+		let mut registry = ForeignRegistry::new();
+		registry.class::<f64, _>("vector", "Vec3", |_| Ok(0.0));
+		registry.constructor("vector", "Vec3", "new(_,_,_)");
+		registry.constructor("vector", "Vec3", "zero()");
+		for signature in &["x", "x=(_)", "+(_)", "-", "[_]", "[_,_]=(_)", "dot(_)"] {
+			registry.method("vector", "Vec3", false, signature, |_| Ok(()));
+		}
+		registry.method("vector", "Vec3", true, "lerp(_,_,_)", |_| Ok(()));
+		registry.method("vector", "Util", true, "version", |_| Ok(()));
+		registry.method("other", "Other", false, "call()", |_| Ok(()));
+		registry
+	}
+
+	// The signature Wren works out for a declaration, by putting the
+	// placeholders back.
+	fn signature_of(declaration: &str) -> String {
+		let mut signature = String::new();
+		let mut chars = declaration.chars().peekable();
+		while let Some(c) = chars.next() {
+			match c {
+				'a' if chars.peek().is_some_and(char::is_ascii_digit) => {
+					while chars.peek().is_some_and(char::is_ascii_digit) {
+						chars.next();
+					}
+					signature.push('_');
+				}
+				' ' => {}
+				_ => signature.push(c),
+			}
+		}
+		signature
+	}
+
+	#[test]
+	fn declarations() {
+		assert_eq!(declaration("update(_,_)"), "update(a0, a1)");
+		assert_eq!(declaration("update()"), "update()");
+		assert_eq!(declaration("count"), "count");
+		assert_eq!(declaration("x=(_)"), "x=(a0)");
+		assert_eq!(declaration("+(_)"), "+(a0)");
+		assert_eq!(declaration("-"), "-");
+		assert_eq!(declaration("[_,_]=(_)"), "[a0, a1]=(a2)");
+		assert_eq!(declaration("is_empty"), "is_empty");
+		assert_eq!(declaration("set_x_y(_,_)"), "set_x_y(a0, a1)");
+	}
+
+	#[test]
+	fn declarations_match_signatures() {
+		for signature in &["update(_,_)", "count", "x=(_)", "+(_)", "-", "[_]", "[_,_]=(_)", "is_empty(_)"] {
+			let declared = declaration(signature);
+			assert_eq!(signature_of(&declared), *signature);
+			assert_eq!(signature_arity(&signature_of(&declared)), signature_arity(signature));
+		}
+	}
+
+	#[test]
+	fn class_source() {
+		let source = registry().class_source("vector", "Vec3");
+		assert_eq!(
+			source,
+			"foreign class Vec3 {\n\
+			 \tconstruct new(a0, a1, a2) {}\n\
+			 \tconstruct zero() {}\n\
+			 \tforeign +(a0)\n\
+			 \tforeign -\n\
+			 \tforeign [a0, a1]=(a2)\n\
+			 \tforeign [a0]\n\
+			 \tforeign dot(a0)\n\
+			 \tforeign x\n\
+			 \tforeign x=(a0)\n\
+			 \tforeign static lerp(a0, a1, a2)\n\
+			 }\n"
+		);
+	}
+
+	#[test]
+	fn module_source() {
+		let registry = registry();
+		let source = registry.source("vector").unwrap();
+		// Sorted by name.
+		assert!(source.starts_with("class Util {\n\tforeign static version\n}\n\nforeign class Vec3 {\n"));
+		assert!(!source.contains("Other"));
+		assert_eq!(registry.source("main"), None);
+	}
+
+	#[test]
+	fn loads_declared_modules() {
+		let mut registry = registry();
+		assert_eq!(registry.resolve("main", "vector").as_deref(), Some("vector"));
+		assert_eq!(registry.resolve("main", "missing"), None);
+		assert_eq!(registry.load("other").as_deref(), Some("class Other {\n\tforeign call()\n}\n"));
+		assert_eq!(registry.load("missing"), None);
+	}
+}
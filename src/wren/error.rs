@@ -0,0 +1,301 @@
+use std::error::Error;
+use std::ffi::NulError;
+use std::fmt;
+use std::mem;
+
+use super::class::EXHAUSTED_CLASS;
+use super::foreign::{MethodKey, MAX_BOUND};
+use super::SlotError;
+use crate::{WrenErrorType, WrenInterpretResult};
+
+/// An error returned from running Wren code.
+#[derive(Debug)]
+pub enum WrenError {
+	/// The source, or a module it imports, failed to compile. Wren reports
+	/// every error it finds, in the order it found them.
+	Compile(Vec<CompileDiagnostic>),
+
+	/// The fiber running the code was aborted by a runtime error.
+	Runtime {
+		message: String,
+
+		/// The call stack at the point of the error, innermost frame first.
+		stack: Vec<Frame>,
+	},
+
+	/// A string passed to Wren contained an interior null byte.
+	Nul(NulError),
+
+	/// A method signature isn't well formed.
+	Signature(String),
+
+	/// The number of arguments given doesn't match the signature's.
+	Arity { signature: String, arity: usize, args: usize },
+
+	/// A value couldn't be converted to or from a slot.
+	Slot(SlotError),
+
+	/// No module with this name has been imported or interpreted.
+	UnknownModule(String),
+
+	/// The module has no top-level variable with this name.
+	UnknownVariable { module: String, name: String },
+
+	/// Wren asked for a foreign method that isn't in the
+	/// [`ForeignRegistry`](super::ForeignRegistry).
+	UnboundMethod(MethodKey),
+
+	/// The foreign method is registered, but the VM has already bound as many
+	/// foreign methods as it can.
+	TooManyForeignMethods(MethodKey),
+}
+
+/// One compile error.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CompileDiagnostic {
+	pub module: String,
+	pub line: u32,
+	pub message: String,
+}
+
+/// One line of a runtime error's stack trace.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Frame {
+	/// The resolved module the method or function is defined in.
+	pub module: String,
+	pub line: u32,
+	/// The name of the method or function.
+	pub function: String,
+}
+
+impl fmt::Display for CompileDiagnostic {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "[{} line {}] [Error] {}", self.module, self.line, self.message)
+	}
+}
+
+impl fmt::Display for Frame {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "[{} line {}] in {}", self.module, self.line, self.function)
+	}
+}
+
+impl fmt::Display for WrenError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			WrenError::Compile(diagnostics) => {
+				write!(f, "compile error")?;
+				for diagnostic in diagnostics {
+					write!(f, "\n{}", diagnostic)?;
+				}
+				Ok(())
+			}
+			WrenError::Runtime { message, stack } => {
+				write!(f, "runtime error: {}", message)?;
+				for frame in stack {
+					write!(f, "\n{}", frame)?;
+				}
+				Ok(())
+			}
+			WrenError::Nul(err) => write!(f, "invalid string: {}", err),
+			WrenError::Signature(signature) => write!(f, "invalid method signature `{}`", signature),
+			WrenError::Arity { signature, arity, args } => {
+				write!(f, "`{}` takes {} arguments, but {} were given", signature, arity, args)
+			}
+			WrenError::Slot(err) => err.fmt(f),
+			WrenError::UnknownModule(module) => write!(f, "module `{}` has not been loaded", module),
+			WrenError::UnknownVariable { module, name } => {
+				write!(f, "module `{}` has no variable `{}`", module, name)
+			}
+			WrenError::UnboundMethod(key) => write!(f, "no foreign method registered for {}", key),
+			WrenError::TooManyForeignMethods(key) => {
+				write!(f, "could not bind {}: a VM can bind at most {} foreign methods", key, MAX_BOUND)
+			}
+		}
+	}
+}
+
+impl Error for WrenError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		match self {
+			WrenError::Nul(err) => Some(err),
+			WrenError::Slot(err) => Some(err),
+			_ => None,
+		}
+	}
+}
+
+impl From<SlotError> for WrenError {
+	fn from(err: SlotError) -> Self {
+		WrenError::Slot(err)
+	}
+}
+
+impl From<NulError> for WrenError {
+	fn from(err: NulError) -> Self {
+		WrenError::Nul(err)
+	}
+}
+
+/// A single report from Wren's error callback.
+#[derive(Clone, Debug)]
+pub struct ErrorEvent {
+	/// Whether this is a compile error, a runtime error, or one line of the
+	/// stack trace that follows a runtime error.
+	pub kind: WrenErrorType,
+
+	/// The resolved module the error occurred in. `None` for runtime errors.
+	pub module: Option<String>,
+
+	/// The line the error occurred on. `None` for runtime errors.
+	pub line: Option<u32>,
+
+	/// The error message, or for stack trace lines the method or function name.
+	pub message: String,
+}
+
+impl fmt::Display for ErrorEvent {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let module = self.module.as_deref().unwrap_or("?");
+		let line = self.line.unwrap_or(0);
+
+		match self.kind {
+			WrenErrorType::Compile => write!(f, "[{} line {}] [Error] {}", module, line, self.message),
+			WrenErrorType::Runtime => write!(f, "[Runtime Error] {}", self.message),
+			WrenErrorType::StackTrace => write!(f, "[{} line {}] in {}", module, line, self.message),
+		}
+	}
+}
+
+// Gathers the calls Wren makes to the error callback while running code, and
+// turns them into a `WrenError` once it's done.
+#[derive(Default)]
+pub(crate) struct ErrorCollector {
+	compile: Vec<CompileDiagnostic>,
+	runtime: Option<String>,
+	stack: Vec<Frame>,
+	unbound: Option<(MethodKey, bool)>,
+}
+
+impl ErrorCollector {
+	pub fn push(&mut self, event: &ErrorEvent) {
+		let module = event.module.clone().unwrap_or_default();
+		let line = event.line.unwrap_or(0);
+
+		match event.kind {
+			WrenErrorType::Compile => self.compile.push(CompileDiagnostic {
+				module,
+				line,
+				message: event.message.clone(),
+			}),
+			WrenErrorType::Runtime => {
+				self.runtime = Some(event.message.clone());
+				self.stack.clear();
+			}
+			WrenErrorType::StackTrace => self.stack.push(Frame {
+				module,
+				line,
+				function: event.message.clone(),
+			}),
+		}
+	}
+
+	fn is_unbound_error(&self) -> bool {
+		match &self.runtime {
+			Some(message) => message.starts_with("Could not find foreign method") || message == EXHAUSTED_CLASS,
+			None => false,
+		}
+	}
+
+	// Whether the runtime error is an import failing to compile, which Wren
+	// reports after the compile errors themselves.
+	fn is_import_compile_error(&self) -> bool {
+		match &self.runtime {
+			Some(message) => message.starts_with("Could not compile module") && !self.compile.is_empty(),
+			None => false,
+		}
+	}
+
+	// Records that binding the foreign method [key] failed, either because it
+	// isn't registered or because the VM ran out of shims.
+	pub fn unbound(&mut self, key: MethodKey, exhausted: bool) {
+		self.unbound = Some((key, exhausted));
+	}
+
+	// Converts [result] into a `Result`, using whatever was collected since the
+	// last call. The collector is left empty.
+	pub fn finish(&mut self, result: WrenInterpretResult) -> Result<(), WrenError> {
+		let collected = mem::take(self);
+
+		match result {
+			WrenInterpretResult::Success => Ok(()),
+			WrenInterpretResult::CompileError => Err(WrenError::Compile(collected.compile)),
+			// Misses are also recorded for methods Wren then finds in its
+			// optional modules, so only use one if that's what the error is.
+			WrenInterpretResult::RuntimeError => Err(match collected.unbound {
+				Some((key, exhausted)) if collected.is_unbound_error() => {
+					if exhausted {
+						WrenError::TooManyForeignMethods(key)
+					} else {
+						WrenError::UnboundMethod(key)
+					}
+				}
+				_ if collected.is_import_compile_error() => WrenError::Compile(collected.compile),
+				_ => WrenError::Runtime {
+					message: collected.runtime.unwrap_or_default(),
+					stack: collected.stack,
+				},
+			}),
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn event(kind: WrenErrorType, module: Option<&str>, line: Option<u32>, message: &str) -> ErrorEvent {
+		ErrorEvent {
+			kind,
+			module: module.map(str::to_string),
+			line,
+			message: message.to_string(),
+		}
+	}
+
+	#[test]
+	fn runtime_error_with_stack() {
+		let mut collector = ErrorCollector::default();
+		collector.push(&event(WrenErrorType::Runtime, None, None, "Null does not implement 'foo'."));
+		collector.push(&event(WrenErrorType::StackTrace, Some("main"), Some(3), "bar(_)"));
+		collector.push(&event(WrenErrorType::StackTrace, Some("main"), Some(7), "(script)"));
+
+		match collector.finish(WrenInterpretResult::RuntimeError) {
+			Err(WrenError::Runtime { message, stack }) => {
+				assert_eq!(message, "Null does not implement 'foo'.");
+				assert_eq!(stack.len(), 2);
+				assert_eq!(stack[0].function, "bar(_)");
+				assert_eq!(stack[1].line, 7);
+			}
+			other => panic!("unexpected {:?}", other),
+		}
+		assert!(collector.finish(WrenInterpretResult::Success).is_ok());
+	}
+
+	#[test]
+	fn import_compile_error() {
+		let mut collector = ErrorCollector::default();
+		collector.push(&event(WrenErrorType::Compile, Some("util"), Some(2), "Error at 'var': Expect expression."));
+		collector.push(&event(WrenErrorType::Runtime, None, None, "Could not compile module 'util'."));
+		collector.push(&event(WrenErrorType::StackTrace, Some("main"), Some(1), "(script)"));
+
+		match collector.finish(WrenInterpretResult::RuntimeError) {
+			Err(WrenError::Compile(diagnostics)) => {
+				assert_eq!(diagnostics.len(), 1);
+				assert_eq!(diagnostics[0].module, "util");
+				assert_eq!(diagnostics[0].line, 2);
+			}
+			other => panic!("unexpected {:?}", other),
+		}
+	}
+}
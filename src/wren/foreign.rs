@@ -0,0 +1,185 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+use std::rc::Rc;
+
+use libc::c_char;
+
+use super::context::{lossy, Context};
+use super::Slots;
+use crate::*;
+
+/// What a foreign method returns. An error aborts the calling fiber with the
+/// error's message.
+pub type ForeignResult = Result<(), Box<dyn Error>>;
+
+/// A foreign method implemented in Rust. The receiver is in slot 0 and the
+/// arguments follow it; the value left in slot 0 is returned to Wren.
+///
+/// Closures are shared between the registry and the VMs that bind them, so
+/// state they need to change goes in a `Cell` or `RefCell`.
+pub type ForeignMethod = Rc<dyn Fn(&mut Slots) -> ForeignResult>;
+
+/// Identifies a foreign method the way Wren asks for it.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct MethodKey {
+	pub module: String,
+	pub class: String,
+	pub is_static: bool,
+	pub signature: String,
+}
+
+impl fmt::Display for MethodKey {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let keyword = if self.is_static { "static " } else { "" };
+		write!(f, "{}{}.{} in module `{}`", keyword, self.class, self.signature, self.module)
+	}
+}
+
+/// The foreign methods a VM can bind, keyed by module, class, staticness and
+/// signature.
+///
+/// ```
+/// # use wren_sys::wren::{ForeignRegistry, VmBuilder};
+/// let scale = 2.0;
+/// let mut registry = ForeignRegistry::new();
+/// registry.method("math", "Math", true, "scale(_)", move |slots| {
+///     let value = slots.get::<f64>(1)?;
+///     Ok(slots.set(0, value * scale)?)
+/// });
+/// let mut vm = VmBuilder::new().foreign(registry).build();
+/// vm.interpret("main", "import \"math\" for Math\nSystem.print(Math.scale(21))")?;
+/// # Ok::<(), wren_sys::wren::WrenError>(())
+/// ```
+///
+/// Scripts don't need to declare what is registered: importing one of the
+/// registry's modules loads its declarations from [`ForeignRegistry::source`].
+/// That module is generated, so it has to be a different one from the script
+/// using it.
+///
+/// Cloning a registry shares its closures, so that the same methods can be
+/// given to several VMs.
+#[derive(Clone, Default)]
+pub struct ForeignRegistry {
+	pub(super) methods: HashMap<MethodKey, ForeignMethod>,
+
+	// The allocators of foreign classes, by module and class name.
+	pub(super) classes: HashMap<(String, String), ForeignMethod>,
+
+	// The constructor signatures of foreign classes, for their declarations.
+	pub(super) constructors: HashMap<(String, String), Vec<String>>,
+}
+
+impl ForeignRegistry {
+	pub fn new() -> Self {
+		ForeignRegistry::default()
+	}
+
+	/// Registers `method` as the implementation of the foreign method with
+	/// `signature` on `class` in the resolved `module`. Registering the same
+	/// method again replaces it.
+	///
+	/// The closure is owned by the registry, and then by the VM it is given
+	/// to.
+	pub fn method<F>(&mut self, module: &str, class: &str, is_static: bool, signature: &str, method: F) -> &mut Self
+	where
+		F: Fn(&mut Slots) -> ForeignResult + 'static,
+	{
+		let key = MethodKey {
+			module: module.to_string(),
+			class: class.to_string(),
+			is_static,
+			signature: signature.to_string(),
+		};
+		self.methods.insert(key, Rc::new(method));
+		self
+	}
+}
+
+// Wren's foreign methods are bare function pointers without any user data, so
+// there is no way to hand Wren a closure directly. Instead, each method a VM
+// binds is given a slot in its context's `bound` table, and Wren gets the shim
+// for that slot, which looks the closure up again through the VM's user data.
+// The shims are the same for every VM.
+const SHIM_ROWS: usize = 64;
+const SHIM_COLUMNS: usize = 16;
+pub(crate) const MAX_BOUND: usize = SHIM_ROWS * SHIM_COLUMNS;
+
+unsafe extern "C" fn shim<const INDEX: usize>(vm: *mut WrenVM) {
+	dispatch(vm, INDEX)
+}
+
+macro_rules! shim_row {
+	($row:literal) => {[
+		shim::<{ $row * 16 }>, shim::<{ $row * 16 + 1 }>, shim::<{ $row * 16 + 2 }>, shim::<{ $row * 16 + 3 }>,
+		shim::<{ $row * 16 + 4 }>, shim::<{ $row * 16 + 5 }>, shim::<{ $row * 16 + 6 }>, shim::<{ $row * 16 + 7 }>,
+		shim::<{ $row * 16 + 8 }>, shim::<{ $row * 16 + 9 }>, shim::<{ $row * 16 + 10 }>, shim::<{ $row * 16 + 11 }>,
+		shim::<{ $row * 16 + 12 }>, shim::<{ $row * 16 + 13 }>, shim::<{ $row * 16 + 14 }>, shim::<{ $row * 16 + 15 }>,
+	]};
+}
+
+macro_rules! shim_table {
+	($($row:literal)*) => { [$(shim_row!($row)),*] };
+}
+
+static SHIMS: [[WrenForeignMethodFn; SHIM_COLUMNS]; SHIM_ROWS] = shim_table!(
+	0 1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 28 29 30 31
+	32 33 34 35 36 37 38 39 40 41 42 43 44 45 46 47 48 49 50 51 52 53 54 55 56 57 58 59 60 61 62 63
+);
+
+// Hands out the next shim of [context]'s VM for [method], or `None` if they're used up.
+pub(crate) fn bind(context: &mut Context, method: ForeignMethod) -> Option<WrenForeignMethodFn> {
+	let index = context.bound.len();
+	if index >= MAX_BOUND {
+		return None;
+	}
+	context.bound.push(method);
+	Some(SHIMS[index / SHIM_COLUMNS][index % SHIM_COLUMNS])
+}
+
+unsafe fn dispatch(vm: *mut WrenVM, index: usize) {
+	// Cloned, and the context let go of, before the closure runs: the closure
+	// gets at the context itself, e.g. to create a handle from a slot.
+	let method = Context::get(vm).bound[index].clone();
+
+	let message = match panic::catch_unwind(AssertUnwindSafe(|| method(&mut Slots::from_raw(vm)))) {
+		Ok(Ok(())) => return,
+		Ok(Err(err)) => err.to_string(),
+		Err(payload) => {
+			// The panic is resumed once the VM returns to Rust.
+			Context::get(vm).panic.get_or_insert(payload);
+			"foreign method panicked".to_string()
+		}
+	};
+
+	let mut slots = Slots::from_raw(vm);
+	let _ = slots.set_str(0, &message);
+	let _ = slots.abort_fiber(0);
+}
+
+pub(crate) unsafe extern "C" fn bind_foreign_method_fn(
+	vm: *mut WrenVM,
+	module: *const c_char,
+	class_name: *const c_char,
+	is_static: bool,
+	signature: *const c_char,
+) -> Option<WrenForeignMethodFn> {
+	let context = Context::get(vm);
+	let key = MethodKey {
+		module: lossy(module).unwrap_or_default(),
+		class: lossy(class_name).unwrap_or_default(),
+		is_static,
+		signature: lossy(signature).unwrap_or_default(),
+	};
+
+	let method = context.foreign.methods.get(&key).cloned();
+	let registered = method.is_some();
+	let bound = method.and_then(|method| bind(context, method));
+	if bound.is_none() {
+		// Wren reports a missing method as a plain runtime error; keep the key
+		// so the `WrenError` can say exactly what wasn't bound.
+		context.errors.unbound(key, registered);
+	}
+	bound
+}
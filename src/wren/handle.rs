@@ -0,0 +1,43 @@
+use std::fmt;
+use std::rc::Rc;
+
+use super::vm::Raw;
+use crate::*;
+
+/// An owned reference to a Wren object, or a method to call.
+///
+/// The object won't be garbage collected while the handle exists. The handle
+/// is released with `wrenReleaseHandle` when dropped, and keeps the VM it
+/// came from alive until then, so don't store one inside a foreign object.
+pub struct Handle {
+	raw: Rc<Raw>,
+	handle: *mut WrenHandle,
+}
+
+impl Handle {
+	pub(crate) unsafe fn from_raw(raw: Rc<Raw>, handle: *mut WrenHandle) -> Self {
+		Handle { raw, handle }
+	}
+
+	/// Returns the raw handle, which stays owned by this value.
+	pub fn as_ptr(&self) -> *mut WrenHandle {
+		self.handle
+	}
+
+	// The VM the handle belongs to.
+	pub(crate) fn vm(&self) -> *mut WrenVM {
+		self.raw.vm
+	}
+}
+
+impl fmt::Debug for Handle {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_tuple("Handle").field(&self.handle).finish()
+	}
+}
+
+impl Drop for Handle {
+	fn drop(&mut self) {
+		unsafe { wrenReleaseHandle(self.raw.vm, self.handle) }
+	}
+}
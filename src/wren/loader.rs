@@ -0,0 +1,222 @@
+use std::borrow::Cow;
+use std::ffi::CString;
+use std::ptr;
+
+use libc::c_char;
+
+use super::context::{lossy, Context};
+use crate::*;
+
+#[cfg(any(feature = "zip", feature = "tar"))]
+mod archive;
+mod chain;
+mod file;
+mod memory;
+
+#[cfg(any(feature = "zip", feature = "tar"))]
+pub use self::archive::ArchiveLoader;
+pub use self::chain::ChainLoader;
+pub use self::file::FileLoader;
+pub use self::memory::MemoryLoader;
+
+/// Finds the source of the modules scripts import.
+///
+/// Wren first resolves the name in an `import` against the module doing the
+/// importing, then loads the module by its resolved name, unless it has been
+/// loaded already. The resolved name is the one used for the module from then
+/// on, e.g. in stack traces and by [`Vm::get_variable`](super::Vm::get_variable).
+///
+/// Modules declared by the VM's [`ForeignRegistry`](super::ForeignRegistry)
+/// are found before asking the loader.
+pub trait ModuleLoader {
+	/// Resolves `name`, as imported from the resolved module `importer`, or
+	/// returns `None` to leave it to the next loader. Defaults to `None`.
+	///
+	/// If no loader resolves it, `name` is used as it is.
+	fn resolve(&mut self, importer: &str, name: &str) -> Option<String> {
+		let _ = (importer, name);
+		None
+	}
+
+	/// Returns the source of the module with the resolved `name`, or `None`
+	/// if this loader doesn't have it.
+	fn load(&mut self, name: &str) -> Option<Cow<'_, str>>;
+}
+
+impl<L: ModuleLoader + ?Sized> ModuleLoader for Box<L> {
+	fn resolve(&mut self, importer: &str, name: &str) -> Option<String> {
+		(**self).resolve(importer, name)
+	}
+
+	fn load(&mut self, name: &str) -> Option<Cow<'_, str>> {
+		(**self).load(name)
+	}
+}
+
+// Resolves a relative import like `./util` or `../lib/util` against the
+// resolved name of the module importing it, and tidies up any other name into
+// `/`-separated segments. Returns `None` for absolute paths and for names that
+// would climb out of the top.
+pub(crate) fn resolve_path(importer: &str, name: &str) -> Option<String> {
+	if name.starts_with('/') || name.contains('\\') {
+		return None;
+	}
+
+	let mut segments = Vec::new();
+	if name == "." || name == ".." || name.starts_with("./") || name.starts_with("../") {
+		segments.extend(importer.split('/'));
+		// Relative to the importer's directory, not the importer itself.
+		segments.pop();
+	}
+	for segment in name.split('/') {
+		match segment {
+			"" | "." => {}
+			".." => {
+				segments.pop()?;
+			}
+			segment => segments.push(segment),
+		}
+	}
+
+	if segments.is_empty() {
+		None
+	} else {
+		Some(segments.join("/"))
+	}
+}
+
+pub(crate) unsafe extern "C" fn resolve_module_fn(vm: *mut WrenVM, importer: *const c_char, name: *const c_char) -> *const c_char {
+	let context = Context::get(vm);
+	let (importer_str, name_str) = (lossy(importer).unwrap_or_default(), lossy(name).unwrap_or_default());
+	let resolved = context.catch(|context| {
+		context.foreign.resolve(&importer_str, &name_str).or_else(|| {
+			let loader = context.loader.as_mut()?;
+			loader.resolve(&importer_str, &name_str)
+		})
+	});
+
+	let resolved = match resolved.flatten() {
+		Some(resolved) if resolved != name_str => resolved,
+		// Handing back the same pointer tells Wren to keep the name it has.
+		_ => return name,
+	};
+
+	// Wren frees the resolved name with its reallocator, which is the C
+	// allocator since the VM is created without one of its own.
+	let resolved = match CString::new(resolved) {
+		Ok(resolved) => resolved,
+		Err(_) => return name,
+	};
+	let bytes = resolved.as_bytes_with_nul();
+	let copy = libc::malloc(bytes.len()) as *mut c_char;
+	if copy.is_null() {
+		return name;
+	}
+	ptr::copy_nonoverlapping(bytes.as_ptr() as *const c_char, copy, bytes.len());
+	copy
+}
+
+pub(crate) unsafe extern "C" fn load_module_fn(vm: *mut WrenVM, name: *const c_char) -> WrenLoadModuleResult {
+	let context = Context::get(vm);
+	let name = lossy(name).unwrap_or_default();
+	let source = context.catch(|context| {
+		let source = match context.foreign.load(&name) {
+			Some(source) => Some(source),
+			None => context.loader.as_mut().and_then(|loader| loader.load(&name)),
+		};
+		// A module with a NUL in it can't be handed to Wren.
+		source.and_then(|source| CString::new(source.into_owned()).ok())
+	});
+
+	match source.flatten() {
+		Some(source) => WrenLoadModuleResult {
+			source: source.into_raw(),
+			on_complete: Some(free_source),
+			user_data: ptr::null_mut(),
+		},
+		// Wren falls back to its optional modules, then reports the module
+		// as missing.
+		None => WrenLoadModuleResult {
+			source: ptr::null(),
+			on_complete: None,
+			user_data: ptr::null_mut(),
+		},
+	}
+}
+
+unsafe extern "C" fn free_source(_vm: *mut WrenVM, _name: *const c_char, result: WrenLoadModuleResult) {
+	drop(CString::from_raw(result.source as *mut c_char));
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::resolve_path;
+
+	#[test]
+	fn relative_paths() {
+		assert_eq!(resolve_path("lib/vector", "./util").as_deref(), Some("lib/util"));
+		assert_eq!(resolve_path("lib/vector", "../util").as_deref(), Some("util"));
+		assert_eq!(resolve_path("lib/math/vector", "../util/./list").as_deref(), Some("lib/util/list"));
+		assert_eq!(resolve_path("main", "./util").as_deref(), Some("util"));
+		assert_eq!(resolve_path("lib/vector", ".").as_deref(), Some("lib"));
+	}
+
+	#[test]
+	fn other_names_are_tidied() {
+		assert_eq!(resolve_path("lib/vector", "util").as_deref(), Some("util"));
+		assert_eq!(resolve_path("main", "lib//util/").as_deref(), Some("lib/util"));
+		assert_eq!(resolve_path("main", "lib/../util").as_deref(), Some("util"));
+	}
+
+	#[test]
+	fn escapes_are_refused() {
+		assert_eq!(resolve_path("main", "../util"), None);
+		assert_eq!(resolve_path("lib/vector", "../../util"), None);
+		assert_eq!(resolve_path("main", "lib/../../util"), None);
+		assert_eq!(resolve_path("main", ".."), None);
+		assert_eq!(resolve_path("main", "/etc/passwd"), None);
+		assert_eq!(resolve_path("main", "lib\\..\\..\\util"), None);
+		assert_eq!(resolve_path("main", ""), None);
+	}
+}
+
+// Helpers for tests that need files on disk.
+#[cfg(test)]
+pub(crate) mod testing {
+	use std::env;
+	use std::fs;
+	use std::path::{Path, PathBuf};
+	use std::process;
+	use std::sync::atomic::{AtomicUsize, Ordering};
+
+	// A directory under the system's temporary one, removed when dropped.
+	pub(crate) struct TempDir(PathBuf);
+
+	impl TempDir {
+		pub fn new() -> Self {
+			static COUNT: AtomicUsize = AtomicUsize::new(0);
+			let name = format!("wren-sys-{}-{}", process::id(), COUNT.fetch_add(1, Ordering::Relaxed));
+			let path = env::temp_dir().join(name);
+			fs::create_dir_all(&path).unwrap();
+			TempDir(path)
+		}
+
+		pub fn write(&self, path: &str, contents: &str) -> PathBuf {
+			let path = self.0.join(path);
+			fs::create_dir_all(path.parent().unwrap()).unwrap();
+			fs::write(&path, contents).unwrap();
+			path
+		}
+
+		pub fn path(&self) -> &Path {
+			&self.0
+		}
+	}
+
+	impl Drop for TempDir {
+		fn drop(&mut self) {
+			let _ = fs::remove_dir_all(&self.0);
+		}
+	}
+}
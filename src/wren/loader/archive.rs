@@ -0,0 +1,268 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Read};
+#[cfg(feature = "zip")]
+use std::io::Seek;
+use std::path::Path;
+
+use super::{resolve_path, MemoryLoader, ModuleLoader};
+
+/// Loads modules from `.zip` and `.tar` archives, each under a namespace of
+/// its own.
+///
+/// The module `foo/bar.wren` in the archive added as `modname` is imported as
+/// `modname:foo/bar`, and can import its neighbours with relative imports like
+/// `./baz`. Archives are read into memory when they're added, and one with a
+/// module over 16 MiB is refused.
+///
+/// ```no_run
+/// # use wren_sys::wren::ArchiveLoader;
+/// # #[cfg(feature = "zip")]
+/// # fn main() -> std::io::Result<()> {
+/// let mut mods = ArchiveLoader::new();
+/// mods.open_zip("modname", "mods/modname.zip")?;
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "zip"))]
+/// # fn main() {}
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ArchiveLoader {
+	archives: HashMap<String, MemoryLoader>,
+}
+
+impl ArchiveLoader {
+	pub fn new() -> Self {
+		ArchiveLoader::default()
+	}
+
+	/// Adds the zip archive read from `reader` as `namespace`, replacing any
+	/// archive added under that name.
+	#[cfg(feature = "zip")]
+	pub fn add_zip<R: Read + Seek>(&mut self, namespace: &str, reader: R) -> io::Result<&mut Self> {
+		let invalid = |err| io::Error::new(io::ErrorKind::InvalidData, err);
+		let mut archive = zip::ZipArchive::new(reader).map_err(invalid)?;
+		let mut modules = MemoryLoader::new();
+		for index in 0..archive.len() {
+			let mut file = archive.by_index(index).map_err(invalid)?;
+			if file.is_file() {
+				let path = file.name().to_string();
+				insert(&mut modules, &path, &mut file)?;
+			}
+		}
+		Ok(self.add(namespace, modules))
+	}
+
+	/// Adds the zip archive at `path` as `namespace`.
+	#[cfg(feature = "zip")]
+	pub fn open_zip(&mut self, namespace: &str, path: impl AsRef<Path>) -> io::Result<&mut Self> {
+		self.add_zip(namespace, File::open(path)?)
+	}
+
+	/// Adds the tar archive read from `reader` as `namespace`, replacing any
+	/// archive added under that name. Compressed archives can be read by
+	/// wrapping `reader` in a decoder.
+	#[cfg(feature = "tar")]
+	pub fn add_tar<R: Read>(&mut self, namespace: &str, reader: R) -> io::Result<&mut Self> {
+		let mut archive = tar::Archive::new(reader);
+		let mut modules = MemoryLoader::new();
+		for entry in archive.entries()? {
+			let mut entry = entry?;
+			if entry.header().entry_type().is_file() {
+				let path = entry.path()?;
+				let path = path.to_str().ok_or_else(|| not_utf8(&path))?.to_string();
+				insert(&mut modules, &path, &mut entry)?;
+			}
+		}
+		Ok(self.add(namespace, modules))
+	}
+
+	/// Adds the tar archive at `path` as `namespace`.
+	#[cfg(feature = "tar")]
+	pub fn open_tar(&mut self, namespace: &str, path: impl AsRef<Path>) -> io::Result<&mut Self> {
+		self.add_tar(namespace, File::open(path)?)
+	}
+
+	/// Removes the archive added as `namespace`, returning whether there was
+	/// one.
+	pub fn remove(&mut self, namespace: &str) -> bool {
+		self.archives.remove(namespace).is_some()
+	}
+
+	fn add(&mut self, namespace: &str, modules: MemoryLoader) -> &mut Self {
+		self.archives.insert(namespace.to_string(), modules);
+		self
+	}
+}
+
+// The largest module read from an archive. Entries can claim any size, so
+// this is what bounds the memory a hostile archive can take up.
+const MAX_MODULE_SIZE: u64 = 16 * 1024 * 1024;
+
+// Reads the archive entry at [path] into [modules] if it's a module. Entries
+// that would resolve outside the archive are skipped.
+fn insert(modules: &mut MemoryLoader, path: &str, entry: &mut impl Read) -> io::Result<()> {
+	let name = match path.strip_suffix(".wren").and_then(|name| resolve_path("", name)) {
+		Some(name) => name,
+		None => return Ok(()),
+	};
+	let mut source = Vec::new();
+	entry.take(MAX_MODULE_SIZE + 1).read_to_end(&mut source)?;
+	if source.len() as u64 > MAX_MODULE_SIZE {
+		let message = format!("`{}` is larger than {} bytes", path, MAX_MODULE_SIZE);
+		return Err(io::Error::new(io::ErrorKind::InvalidData, message));
+	}
+	let source = String::from_utf8(source).map_err(|_| not_utf8(Path::new(path)))?;
+	modules.insert(&name, source);
+	Ok(())
+}
+
+fn not_utf8(path: &Path) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, format!("`{}` isn't valid UTF-8", path.display()))
+}
+
+impl ModuleLoader for ArchiveLoader {
+	// Namespaced imports are looked up in their archive, and relative imports
+	// in the archive of the module importing them. Anything else is left to
+	// other loaders.
+	fn resolve(&mut self, importer: &str, name: &str) -> Option<String> {
+		let (namespace, path) = match name.split_once(':') {
+			Some((namespace, path)) => (namespace, resolve_path("", path)?),
+			None => {
+				let (namespace, importer) = importer.split_once(':')?;
+				if !(name.starts_with("./") || name.starts_with("../")) {
+					return None;
+				}
+				(namespace, resolve_path(importer, name)?)
+			}
+		};
+		let modules = self.archives.get_mut(namespace)?;
+		modules.resolve("", &path).map(|path| format!("{}:{}", namespace, path))
+	}
+
+	fn load(&mut self, name: &str) -> Option<Cow<'_, str>> {
+		let (namespace, path) = name.split_once(':')?;
+		self.archives.get_mut(namespace)?.load(path)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const FILES: &[(&str, &str)] = &[
+		("lib/vector.wren", "class Vector {}"),
+		("lib/util.wren", "import \"./vector\" for Vector"),
+		("main.wren", "import \"./lib/util\""),
+		("README.md", "not a module"),
+		("../escape.wren", "outside"),
+		("/absolute.wren", "outside"),
+		("lib/../inner.wren", "inside"),
+	];
+
+	#[cfg(feature = "zip")]
+	fn zip() -> io::Cursor<Vec<u8>> {
+		use std::io::Write;
+		use zip::write::SimpleFileOptions;
+
+		let mut writer = zip::ZipWriter::new(io::Cursor::new(Vec::new()));
+		writer.add_directory("lib/", SimpleFileOptions::default()).unwrap();
+		for (path, source) in FILES {
+			writer.start_file(*path, SimpleFileOptions::default()).unwrap();
+			writer.write_all(source.as_bytes()).unwrap();
+		}
+		let mut archive = writer.finish().unwrap();
+		archive.set_position(0);
+		archive
+	}
+
+	#[cfg(feature = "tar")]
+	fn tar() -> Vec<u8> {
+		let mut builder = tar::Builder::new(Vec::new());
+		for (path, source) in FILES {
+			let mut header = tar::Header::new_gnu();
+			// `set_path` refuses `..`, which is what's being tested.
+			header.as_gnu_mut().unwrap().name[..path.len()].copy_from_slice(path.as_bytes());
+			header.set_size(source.len() as u64);
+			header.set_mode(0o644);
+			header.set_entry_type(tar::EntryType::Regular);
+			header.set_cksum();
+			builder.append(&header, source.as_bytes()).unwrap();
+		}
+		builder.into_inner().unwrap()
+	}
+
+	fn check(loader: &mut ArchiveLoader) {
+		assert_eq!(loader.resolve("main", "mods:lib/vector").as_deref(), Some("mods:lib/vector"));
+		assert_eq!(loader.resolve("main", "mods:./lib/../main").as_deref(), Some("mods:main"));
+		assert_eq!(loader.load("mods:lib/vector").as_deref(), Some("class Vector {}"));
+		assert_eq!(loader.resolve("main", "mods:README"), None);
+		assert_eq!(loader.resolve("main", "mods:lib"), None);
+
+		// Relative imports stay in the importer's archive.
+		assert_eq!(loader.resolve("mods:lib/util", "./vector").as_deref(), Some("mods:lib/vector"));
+		assert_eq!(loader.resolve("mods:lib/util", "../main").as_deref(), Some("mods:main"));
+		assert_eq!(loader.resolve("mods:main", "../main"), None);
+		assert_eq!(loader.resolve("main", "./vector"), None);
+		assert_eq!(loader.resolve("mods:main", "lib/vector"), None);
+
+		// Entries leading outside the archive are skipped, others normalized.
+		assert_eq!(loader.resolve("main", "mods:../escape"), None);
+		assert_eq!(loader.resolve("main", "mods:escape"), None);
+		assert_eq!(loader.resolve("main", "mods:absolute"), None);
+		assert_eq!(loader.load("mods:escape"), None);
+		assert_eq!(loader.load("mods:inner").as_deref(), Some("inside"));
+
+		// Unknown namespaces.
+		assert_eq!(loader.resolve("main", "other:lib/vector"), None);
+		assert_eq!(loader.resolve("other:main", "./lib/vector"), None);
+		assert_eq!(loader.load("other:lib/vector"), None);
+		assert_eq!(loader.load("lib/vector"), None);
+
+		assert!(loader.remove("mods"));
+		assert!(!loader.remove("mods"));
+		assert_eq!(loader.resolve("main", "mods:lib/vector"), None);
+	}
+
+	#[cfg(feature = "zip")]
+	#[test]
+	fn zip_archives() {
+		let mut loader = ArchiveLoader::new();
+		loader.add_zip("mods", zip()).unwrap();
+		check(&mut loader);
+	}
+
+	#[cfg(feature = "tar")]
+	#[test]
+	fn tar_archives() {
+		let mut loader = ArchiveLoader::new();
+		loader.add_tar("mods", tar().as_slice()).unwrap();
+		check(&mut loader);
+	}
+
+	#[test]
+	fn oversized_modules() {
+		let mut modules = MemoryLoader::new();
+		let err = insert(&mut modules, "huge.wren", &mut io::repeat(b'a')).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+		assert!(!modules.contains("huge"));
+
+		let mut source = io::repeat(b'a').take(MAX_MODULE_SIZE);
+		insert(&mut modules, "large.wren", &mut source).unwrap();
+		assert!(modules.contains("large"));
+	}
+
+	#[test]
+	fn non_utf8_modules() {
+		let err = insert(&mut MemoryLoader::new(), "bad.wren", &mut &b"\xff"[..]).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+	}
+
+	#[cfg(feature = "zip")]
+	#[test]
+	fn invalid_zip() {
+		let err = ArchiveLoader::new().add_zip("mods", io::Cursor::new(b"not a zip".to_vec())).unwrap_err();
+		assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+	}
+}
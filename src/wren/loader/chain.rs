@@ -0,0 +1,87 @@
+use std::borrow::Cow;
+
+use super::ModuleLoader;
+
+/// Tries a list of loaders in order, using the first that resolves or loads a
+/// module.
+///
+/// ```
+/// # use wren_sys::wren::{ChainLoader, FileLoader, MemoryLoader};
+/// # let builtins = MemoryLoader::new();
+/// let mut loader = ChainLoader::new();
+/// loader.push(builtins).push(FileLoader::new("scripts"));
+/// ```
+#[derive(Default)]
+pub struct ChainLoader {
+	loaders: Vec<Box<dyn ModuleLoader>>,
+}
+
+impl ChainLoader {
+	pub fn new() -> Self {
+		ChainLoader::default()
+	}
+
+	/// Adds `loader` after the loaders already in the chain.
+	pub fn push(&mut self, loader: impl ModuleLoader + 'static) -> &mut Self {
+		self.loaders.push(Box::new(loader));
+		self
+	}
+}
+
+impl ModuleLoader for ChainLoader {
+	fn resolve(&mut self, importer: &str, name: &str) -> Option<String> {
+		self.loaders.iter_mut().find_map(|loader| loader.resolve(importer, name))
+	}
+
+	fn load(&mut self, name: &str) -> Option<Cow<'_, str>> {
+		self.loaders.iter_mut().find_map(|loader| loader.load(name))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::wren::MemoryLoader;
+
+	// A loader that only knows how to load, like most custom ones.
+	struct Fixed(&'static str, &'static str);
+
+	impl ModuleLoader for Fixed {
+		fn load(&mut self, name: &str) -> Option<Cow<'_, str>> {
+			if name == self.0 {
+				Some(Cow::Borrowed(self.1))
+			} else {
+				None
+			}
+		}
+	}
+
+	#[test]
+	fn resolves_past_loaders_without_resolve() {
+		let mut modules = MemoryLoader::new();
+		modules.insert("lib/util", "var Util = 1");
+
+		let mut chain = ChainLoader::new();
+		chain.push(Fixed("config", "var Debug = true")).push(modules);
+
+		assert_eq!(chain.resolve("lib/vector", "./util").as_deref(), Some("lib/util"));
+		assert_eq!(chain.resolve("main", "config"), None);
+		assert_eq!(chain.load("config").as_deref(), Some("var Debug = true"));
+		assert_eq!(chain.load("lib/util").as_deref(), Some("var Util = 1"));
+		assert_eq!(chain.load("missing"), None);
+	}
+
+	#[test]
+	fn first_loader_wins() {
+		let mut first = MemoryLoader::new();
+		first.insert("util", "var Source = \"first\"");
+		let mut second = MemoryLoader::new();
+		second.insert("util", "var Source = \"second\"").insert("other", "");
+
+		let mut chain = ChainLoader::new();
+		chain.push(first).push(second);
+
+		assert_eq!(chain.load("util").as_deref(), Some("var Source = \"first\""));
+		assert_eq!(chain.resolve("main", "other").as_deref(), Some("other"));
+	}
+}
@@ -0,0 +1,163 @@
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::{resolve_path, ModuleLoader};
+
+/// Loads modules from `.wren` files under a list of root directories.
+///
+/// A module's name is the path of its file under a root, without the
+/// extension: `import "lib/vector"` reads `lib/vector.wren` from the first
+/// root that has it. Relative imports are resolved against the importing
+/// module's path, so `import "./util"` from `lib/vector` imports `lib/util`.
+/// Either way, that resolved name is the one Wren shows in stack traces.
+///
+/// Imports that lead out of every root, with `..` or through a symlink, are
+/// refused.
+///
+/// ```
+/// # use wren_sys::wren::FileLoader;
+/// let mut loader = FileLoader::new("scripts");
+/// loader.add_root("wren_modules");
+/// ```
+#[derive(Clone, Debug)]
+pub struct FileLoader {
+	roots: Vec<PathBuf>,
+}
+
+impl FileLoader {
+	/// Creates a loader reading modules from under `root`.
+	pub fn new(root: impl Into<PathBuf>) -> Self {
+		FileLoader { roots: vec![root.into()] }
+	}
+
+	/// Adds `root` to the directories searched, after those already added.
+	pub fn add_root(&mut self, root: impl Into<PathBuf>) -> &mut Self {
+		self.roots.push(root.into());
+		self
+	}
+
+	/// Returns the file the module with the resolved `name` is read from, or
+	/// `None` if no root has it.
+	pub fn path(&self, name: &str) -> Option<PathBuf> {
+		// Only names as `resolve` leaves them, so `load` can't be handed a way
+		// out of the roots either.
+		if name.contains(':') || resolve_path("", name).as_deref() != Some(name) {
+			return None;
+		}
+		self.roots.iter().find_map(|root| contained(root, &root.join(format!("{}.wren", name))))
+	}
+}
+
+// Returns the canonical [path] if it is a file inside [root].
+fn contained(root: &Path, path: &Path) -> Option<PathBuf> {
+	let root = root.canonicalize().ok()?;
+	let path = path.canonicalize().ok()?;
+	if path.starts_with(&root) && path.is_file() {
+		Some(path)
+	} else {
+		None
+	}
+}
+
+impl ModuleLoader for FileLoader {
+	fn resolve(&mut self, importer: &str, name: &str) -> Option<String> {
+		resolve_path(importer, name).filter(|name| self.path(name).is_some())
+	}
+
+	fn load(&mut self, name: &str) -> Option<Cow<'_, str>> {
+		fs::read_to_string(self.path(name)?).ok().map(Cow::Owned)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::wren::loader::testing::TempDir;
+
+	fn fixture() -> TempDir {
+		let dir = TempDir::new();
+		dir.write("scripts/main.wren", "import \"./lib/vector\"");
+		dir.write("scripts/lib/vector.wren", "var Vector = 1");
+		dir.write("scripts/lib/util.wren", "var Util = 1");
+		dir.write("modules/lib/util.wren", "var Util = 2");
+		dir.write("modules/json.wren", "var Json = 1");
+		dir.write("secret.wren", "var Secret = 1");
+		dir
+	}
+
+	fn loader(dir: &TempDir) -> FileLoader {
+		let mut loader = FileLoader::new(dir.path().join("scripts"));
+		loader.add_root(dir.path().join("modules"));
+		loader
+	}
+
+	#[test]
+	fn resolves_and_loads() {
+		let dir = fixture();
+		let mut loader = loader(&dir);
+
+		assert_eq!(loader.resolve("main", "./lib/vector").as_deref(), Some("lib/vector"));
+		assert_eq!(loader.resolve("lib/vector", "./util").as_deref(), Some("lib/util"));
+		assert_eq!(loader.resolve("lib/vector", "../main").as_deref(), Some("main"));
+		assert_eq!(loader.resolve("main", "missing"), None);
+		assert_eq!(loader.load("lib/vector").as_deref(), Some("var Vector = 1"));
+		assert_eq!(loader.load("missing"), None);
+	}
+
+	#[test]
+	fn searches_roots_in_order() {
+		let dir = fixture();
+		let mut loader = loader(&dir);
+
+		assert_eq!(loader.load("lib/util").as_deref(), Some("var Util = 1"));
+		assert_eq!(loader.load("json").as_deref(), Some("var Json = 1"));
+		assert_eq!(loader.resolve("lib/vector", "../json").as_deref(), Some("json"));
+	}
+
+	#[test]
+	fn refuses_escapes() {
+		let dir = fixture();
+		let mut loader = loader(&dir);
+
+		assert_eq!(loader.resolve("main", "../secret"), None);
+		assert_eq!(loader.resolve("lib/vector", "../../secret"), None);
+		assert_eq!(loader.resolve("main", "lib/../../secret"), None);
+
+		// `load` is only handed resolved names, but checks anyway.
+		assert_eq!(loader.path("../secret"), None);
+		assert_eq!(loader.path("lib/../main"), None);
+		assert_eq!(loader.path("./main"), None);
+		let absolute = dir.path().join("secret");
+		assert_eq!(loader.path(absolute.to_str().unwrap()), None);
+		assert_eq!(loader.path("..\\secret"), None);
+		assert_eq!(loader.path("c:secret"), None);
+		assert_eq!(loader.load("../secret"), None);
+	}
+
+	#[test]
+	fn only_loads_files() {
+		let dir = fixture();
+		fs::create_dir_all(dir.path().join("scripts/folder.wren")).unwrap();
+		let loader = loader(&dir);
+		assert_eq!(loader.path("folder"), None);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn refuses_symlinks_out_of_roots() {
+		use std::os::unix::fs::symlink;
+
+		let dir = fixture();
+		symlink(dir.path().join("secret.wren"), dir.path().join("scripts/linked.wren")).unwrap();
+		symlink(dir.path(), dir.path().join("scripts/up")).unwrap();
+		symlink(dir.path().join("scripts/lib/util.wren"), dir.path().join("scripts/alias.wren")).unwrap();
+		let mut loader = loader(&dir);
+
+		assert_eq!(loader.path("linked"), None);
+		assert_eq!(loader.path("up/secret"), None);
+		assert_eq!(loader.resolve("main", "./linked"), None);
+		// Links that stay inside the root are fine.
+		assert_eq!(loader.load("alias").as_deref(), Some("var Util = 1"));
+	}
+}
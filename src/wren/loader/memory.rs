@@ -0,0 +1,68 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+use super::{resolve_path, ModuleLoader};
+
+/// Loads modules from sources held in memory, by name.
+///
+/// Names are `/`-separated paths, so that modules can import each other with
+/// relative imports like `./util`, as they would from files.
+///
+/// ```
+/// # use wren_sys::wren::{MemoryLoader, VmBuilder};
+/// let mut modules = MemoryLoader::new();
+/// modules.insert("greeting", "var Greeting = \"Hello\"");
+/// let mut vm = VmBuilder::new().loader(modules).build();
+/// vm.interpret("main", "import \"greeting\" for Greeting")?;
+/// # Ok::<(), wren_sys::wren::WrenError>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct MemoryLoader {
+	modules: HashMap<String, Cow<'static, str>>,
+}
+
+impl MemoryLoader {
+	pub fn new() -> Self {
+		MemoryLoader::default()
+	}
+
+	/// Adds the module `name`, replacing any module of that name.
+	pub fn insert(&mut self, name: &str, source: impl Into<Cow<'static, str>>) -> &mut Self {
+		self.modules.insert(name.to_string(), source.into());
+		self
+	}
+
+	/// Removes the module `name`, returning its source.
+	pub fn remove(&mut self, name: &str) -> Option<Cow<'static, str>> {
+		self.modules.remove(name)
+	}
+
+	/// Returns whether there is a module `name`.
+	pub fn contains(&self, name: &str) -> bool {
+		self.modules.contains_key(name)
+	}
+
+	/// Returns the name and source of every module, in no particular order.
+	pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+		self.modules.iter().map(|(name, source)| (name.as_str(), source.as_ref()))
+	}
+}
+
+impl From<HashMap<String, String>> for MemoryLoader {
+	fn from(modules: HashMap<String, String>) -> Self {
+		let modules = modules.into_iter().map(|(name, source)| (name, Cow::Owned(source))).collect();
+		MemoryLoader { modules }
+	}
+}
+
+impl ModuleLoader for MemoryLoader {
+	// Names are only claimed if they are here, so other loaders in a chain
+	// still get to resolve the rest.
+	fn resolve(&mut self, importer: &str, name: &str) -> Option<String> {
+		resolve_path(importer, name).filter(|name| self.contains(name))
+	}
+
+	fn load(&mut self, name: &str) -> Option<Cow<'_, str>> {
+		self.modules.get(name).map(|source| Cow::Borrowed(source.as_ref()))
+	}
+}
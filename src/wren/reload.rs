@@ -0,0 +1,358 @@
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::SystemTime;
+
+use super::{FileLoader, ForeignRegistry, ModuleLoader, Vm, VmBuilder, WrenError};
+
+type ConfigureFn = Box<dyn Fn(VmBuilder) -> VmBuilder>;
+type MigrateFn = Box<dyn FnMut(&mut Vm, &mut Vm) -> Result<(), WrenError>>;
+
+// The files a VM's loader has read, with the module each was loaded as and
+// when it was last modified at the time.
+type Watched = Rc<RefCell<HashMap<PathBuf, (String, Option<SystemTime>)>>>;
+
+/// Reruns a script in a fresh VM whenever one of the files it loaded changes,
+/// for use during development.
+///
+/// Wren never loads a module twice, so picking up an edit means starting
+/// over: on a change, a new VM is built with the same configuration and
+/// foreign methods, and the entry module is interpreted in it. If that
+/// succeeds the new VM replaces the old one; if it fails, e.g. because the
+/// edit doesn't compile, the old VM is kept and the error reported.
+///
+/// Files are watched by polling their modification times, so call
+/// [`Reloader::poll`] every so often, like once a frame.
+///
+/// Handles belong to the VM that made them, so fetch them again whenever a
+/// reload replaces the VM:
+///
+/// ```no_run
+/// # use wren_sys::wren::{FileLoader, ForeignRegistry, Handle, Reloader};
+/// # fn main() -> Result<(), wren_sys::wren::WrenError> {
+/// # let registry = ForeignRegistry::new();
+/// let mut reloader = Reloader::new(FileLoader::new("scripts"), "main").foreign(registry);
+/// let mut vm = reloader.start()?;
+/// let mut game = vm.get_variable::<Handle>("main", "Game")?;
+/// loop {
+///     if let Some(report) = reloader.poll(&mut vm) {
+///         println!("{}", report);
+///         if report.replaced() {
+///             game = vm.get_variable("main", "Game")?;
+///         }
+///     }
+///     vm.call::<(), _, _>(&game, "update()", ())?;
+/// }
+/// # }
+/// ```
+pub struct Reloader {
+	loader: FileLoader,
+	entry: String,
+	foreign: ForeignRegistry,
+	configure: ConfigureFn,
+	migrate: Option<MigrateFn>,
+	watched: Watched,
+}
+
+/// What [`Reloader::poll`] found and did.
+#[derive(Debug)]
+pub struct ReloadReport {
+	/// The modules whose files changed, by resolved name.
+	pub changed: Vec<(String, PathBuf)>,
+
+	/// Whether the new VM replaced the old one. On an error the old VM is
+	/// kept, along with any handles taken from it.
+	pub result: Result<(), WrenError>,
+}
+
+impl Reloader {
+	/// Creates a reloader running the module `entry` from `loader`.
+	pub fn new(loader: FileLoader, entry: &str) -> Self {
+		Reloader {
+			loader,
+			entry: entry.to_string(),
+			foreign: ForeignRegistry::new(),
+			configure: Box::new(|builder| builder),
+			migrate: None,
+			watched: Watched::default(),
+		}
+	}
+
+	/// Sets the foreign methods every VM binds.
+	pub fn foreign(mut self, registry: ForeignRegistry) -> Self {
+		self.foreign = registry;
+		self
+	}
+
+	/// Sets a closure configuring every VM, e.g. with a `write` closure. The
+	/// loader and foreign methods are set by the reloader.
+	pub fn configure<F>(mut self, configure: F) -> Self
+	where
+		F: Fn(VmBuilder) -> VmBuilder + 'static,
+	{
+		self.configure = Box::new(configure);
+		self
+	}
+
+	/// Sets a closure called with the old VM and the new one once the entry
+	/// module has run in the new one, to carry state over. An error keeps the
+	/// old VM.
+	pub fn migrate<F>(mut self, migrate: F) -> Self
+	where
+		F: FnMut(&mut Vm, &mut Vm) -> Result<(), WrenError> + 'static,
+	{
+		self.migrate = Some(Box::new(migrate));
+		self
+	}
+
+	/// Builds the first VM and runs the entry module in it.
+	pub fn start(&mut self) -> Result<Vm, WrenError> {
+		let watched = Watched::default();
+		let vm = self.build(&watched)?;
+		self.watched = watched;
+		Ok(vm)
+	}
+
+	/// Reloads `vm` if any of the files it loaded have changed since, and
+	/// reports what happened. Returns `None` if nothing changed.
+	///
+	/// If the report says `vm` was replaced, handles taken from it before
+	/// belong to the old VM and can't be used with the new one.
+	pub fn poll(&mut self, vm: &mut Vm) -> Option<ReloadReport> {
+		let changed = self.changed();
+		if changed.is_empty() {
+			None
+		} else {
+			Some(self.reload_changed(vm, changed))
+		}
+	}
+
+	/// Reloads `vm` whether or not anything changed.
+	pub fn reload(&mut self, vm: &mut Vm) -> ReloadReport {
+		let changed = self.changed();
+		self.reload_changed(vm, changed)
+	}
+
+	fn changed(&self) -> Vec<(String, PathBuf)> {
+		let mut changed: Vec<_> = self
+			.watched
+			.borrow()
+			.iter()
+			.filter(|(path, (_, modified))| last_modified(path) != *modified)
+			.map(|(path, (module, _))| (module.clone(), path.clone()))
+			.collect();
+		changed.sort();
+		changed
+	}
+
+	fn reload_changed(&mut self, vm: &mut Vm, changed: Vec<(String, PathBuf)>) -> ReloadReport {
+		let watched = Watched::default();
+		let result = self.build(&watched).and_then(|mut new| {
+			if let Some(migrate) = &mut self.migrate {
+				migrate(vm, &mut new)?;
+			}
+			Ok(new)
+		});
+
+		let result = match result {
+			Ok(new) => {
+				*vm = new;
+				self.watched = watched;
+				Ok(())
+			}
+			Err(err) => {
+				// Wait for the next change before trying again, which may be to
+				// a file only the failed attempt got to.
+				let mut current = self.watched.borrow_mut();
+				for (path, (_, modified)) in current.iter_mut() {
+					*modified = last_modified(path);
+				}
+				for (path, watch) in watched.take() {
+					current.entry(path).or_insert(watch);
+				}
+				Err(err)
+			}
+		};
+		ReloadReport { changed, result }
+	}
+
+	fn build(&self, watched: &Watched) -> Result<Vm, WrenError> {
+		let mut loader = Watching {
+			loader: self.loader.clone(),
+			watched: watched.clone(),
+		};
+		let source = loader.load(&self.entry).map(Cow::into_owned);
+
+		let builder = VmBuilder::new().foreign(self.foreign.clone());
+		let mut vm = (self.configure)(builder).loader(loader).build();
+		match source {
+			Some(source) => vm.interpret(&self.entry, &source).map(|_| vm),
+			None => Err(WrenError::UnknownModule(self.entry.clone())),
+		}
+	}
+}
+
+impl ReloadReport {
+	/// Whether the VM passed to [`Reloader::poll`] or [`Reloader::reload`] was
+	/// replaced by a new one.
+	pub fn replaced(&self) -> bool {
+		self.result.is_ok()
+	}
+}
+
+impl fmt::Display for ReloadReport {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match &self.result {
+			Ok(()) => write!(f, "reloaded")?,
+			Err(_) => write!(f, "kept the old VM")?,
+		}
+		if !self.changed.is_empty() {
+			let modules: Vec<&str> = self.changed.iter().map(|(module, _)| module.as_str()).collect();
+			write!(f, " after changes to {}", modules.join(", "))?;
+		}
+		match &self.result {
+			Ok(()) => Ok(()),
+			Err(err) => write!(f, ": {}", err),
+		}
+	}
+}
+
+fn last_modified(path: &Path) -> Option<SystemTime> {
+	fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+// A `FileLoader` noting down every file it reads, including those imported
+// long after the entry module has run.
+struct Watching {
+	loader: FileLoader,
+	watched: Watched,
+}
+
+impl ModuleLoader for Watching {
+	fn resolve(&mut self, importer: &str, name: &str) -> Option<String> {
+		self.loader.resolve(importer, name)
+	}
+
+	fn load(&mut self, name: &str) -> Option<Cow<'_, str>> {
+		if let Some(path) = self.loader.path(name) {
+			// Noted before reading, so an edit made meanwhile isn't missed.
+			let modified = last_modified(&path);
+			self.watched.borrow_mut().insert(path, (name.to_string(), modified));
+		}
+		self.loader.load(name)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::fs::File;
+	use std::time::Duration;
+
+	use super::*;
+	use crate::wren::loader::testing::TempDir;
+	use crate::wren::Handle;
+
+	fn touch(path: &Path) {
+		let later = SystemTime::now() + Duration::from_secs(60);
+		File::options().write(true).open(path).unwrap().set_modified(later).unwrap();
+	}
+
+	#[test]
+	fn changed() {
+		let dir = TempDir::new();
+		let main = dir.write("main.wren", "import \"lib/util\"").canonicalize().unwrap();
+		let util = dir.write("lib/util.wren", "").canonicalize().unwrap();
+		dir.write("unused.wren", "");
+
+		// What the VM's loader would note down while running `main`.
+		let reloader = Reloader::new(FileLoader::new(dir.path()), "main");
+		let mut loader = Watching {
+			loader: reloader.loader.clone(),
+			watched: reloader.watched.clone(),
+		};
+		assert!(loader.load("main").is_some());
+		assert!(loader.load("lib/util").is_some());
+		assert!(loader.load("missing").is_none());
+		assert!(reloader.changed().is_empty());
+
+		touch(&dir.path().join("unused.wren"));
+		assert!(reloader.changed().is_empty());
+
+		touch(&util);
+		assert_eq!(reloader.changed(), vec![("lib/util".to_string(), util.clone())]);
+
+		fs::remove_file(&main).unwrap();
+		assert_eq!(
+			reloader.changed(),
+			vec![("lib/util".to_string(), util), ("main".to_string(), main)]
+		);
+	}
+
+	// Rewrites [path] under [dir], dated [minutes] from now so the change is
+	// seen even within the file system's timestamp resolution.
+	fn edit(dir: &TempDir, path: &str, contents: &str, minutes: u64) -> PathBuf {
+		let path = dir.write(path, contents);
+		let later = SystemTime::now() + Duration::from_secs(minutes * 60);
+		File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+		path.canonicalize().unwrap()
+	}
+
+	#[test]
+	fn polls() {
+		let dir = TempDir::new();
+		dir.write("main.wren", "import \"lib/util\" for Util\nvar Version = Util.version");
+		dir.write("lib/util.wren", "class Util {\n\tstatic version { 1 }\n}");
+
+		let mut reloader = Reloader::new(FileLoader::new(dir.path()), "main");
+		let mut vm = reloader.start().unwrap();
+		assert_eq!(vm.get_variable::<f64>("main", "Version").unwrap(), 1.0);
+		assert!(reloader.poll(&mut vm).is_none());
+
+		// A change that compiles replaces the VM.
+		let util = edit(&dir, "lib/util.wren", "class Util {\n\tstatic version { 2 }\n}", 1);
+		let report = reloader.poll(&mut vm).unwrap();
+		assert!(report.replaced(), "{}", report);
+		assert_eq!(report.changed, vec![("lib/util".to_string(), util)]);
+		assert_eq!(vm.get_variable::<f64>("main", "Version").unwrap(), 2.0);
+		let util_class: Handle = vm.get_variable("lib/util", "Util").unwrap();
+
+		// One that doesn't keeps the old VM, and the handles taken from it.
+		edit(&dir, "lib/util.wren", "class Util {\n\tstatic version {", 2);
+		let report = reloader.poll(&mut vm).unwrap();
+		assert!(!report.replaced());
+		assert!(matches!(report.result, Err(WrenError::Compile(_))), "{}", report);
+		assert_eq!(vm.get_variable::<f64>("main", "Version").unwrap(), 2.0);
+		assert_eq!(vm.call::<f64, _, _>(&util_class, "version", ()).unwrap(), 2.0);
+
+		// Nothing is retried until the files change again.
+		assert!(reloader.poll(&mut vm).is_none());
+		edit(&dir, "lib/util.wren", "class Util {\n\tstatic version { 3 }\n}", 3);
+		assert!(reloader.poll(&mut vm).unwrap().replaced());
+		assert_eq!(vm.get_variable::<f64>("main", "Version").unwrap(), 3.0);
+	}
+
+	#[test]
+	fn report() {
+		let changed = vec![("main".to_string(), PathBuf::from("main.wren"))];
+		let report = ReloadReport {
+			changed: changed.clone(),
+			result: Ok(()),
+		};
+		assert!(report.replaced());
+		assert_eq!(report.to_string(), "reloaded after changes to main");
+
+		let report = ReloadReport {
+			changed,
+			result: Err(WrenError::UnknownModule("main".to_string())),
+		};
+		assert!(!report.replaced());
+		assert_eq!(
+			report.to_string(),
+			"kept the old VM after changes to main: module `main` has not been loaded"
+		);
+	}
+}
@@ -0,0 +1,357 @@
+use std::error::Error;
+use std::fmt;
+use std::marker::PhantomData;
+use std::slice;
+use std::str;
+
+use libc::{c_char, c_int, c_void};
+
+use super::context::Context;
+use super::Handle;
+use crate::*;
+
+/// An error from reading or writing a slot.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SlotError {
+	/// The slot is past the end of the slots available.
+	OutOfBounds { slot: usize, count: usize },
+
+	/// The slot holds a value of another type.
+	Type { slot: usize, expected: WrenType, found: WrenType },
+
+	/// The element index is past the end of the list in the slot.
+	IndexOutOfBounds { slot: usize, index: isize, count: usize },
+
+	/// The value in the slot can't be used as a map key. Only bools, numbers,
+	/// null and strings are accepted: Wren also allows classes and ranges, but
+	/// the C API can't tell those apart from other objects.
+	InvalidKey { slot: usize, found: WrenType },
+
+	/// More slots were asked for than Wren can address.
+	TooManySlots { count: usize },
+
+	/// The string in the slot isn't valid UTF-8.
+	Utf8 { slot: usize },
+
+	/// The number doesn't convert to the integer type `target` without losing
+	/// its value, either because it isn't whole or is out of range. Also used
+	/// for integers too large to store exactly in a Wren number.
+	Integer { slot: usize, value: f64, target: &'static str },
+
+	/// The handle belongs to a different VM.
+	WrongVm { slot: usize },
+
+	/// The foreign object in the slot doesn't hold a value of the `expected`
+	/// Rust type. `found` is the type it does hold, if it was created by this
+	/// crate.
+	ForeignType { slot: usize, expected: &'static str, found: Option<&'static str> },
+
+	/// The module has no class with this name.
+	UnknownClass { module: String, class: String },
+}
+
+impl fmt::Display for SlotError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			SlotError::OutOfBounds { slot, count } => {
+				write!(f, "slot {} is out of bounds ({} slots available)", slot, count)
+			}
+			SlotError::Type { slot, expected, found } => {
+				write!(f, "slot {} holds {:?}, expected {:?}", slot, found, expected)
+			}
+			SlotError::IndexOutOfBounds { slot, index, count } => {
+				write!(f, "index {} is out of bounds for the list in slot {} ({} elements)", index, slot, count)
+			}
+			SlotError::InvalidKey { slot, found } => {
+				write!(f, "slot {} holds {:?}, which is not a valid map key", slot, found)
+			}
+			SlotError::TooManySlots { count } => write!(f, "{} slots is more than Wren can address", count),
+			SlotError::Utf8 { slot } => write!(f, "slot {} holds a string that is not valid UTF-8", slot),
+			SlotError::Integer { slot, value, target } => {
+				write!(f, "{} in slot {} does not convert to {} exactly", value, slot, target)
+			}
+			SlotError::WrongVm { slot } => write!(f, "the handle for slot {} belongs to another VM", slot),
+			SlotError::ForeignType { slot, expected, found: Some(found) } => {
+				write!(f, "slot {} holds a foreign {}, expected {}", slot, found, expected)
+			}
+			SlotError::ForeignType { slot, expected, found: None } => {
+				write!(f, "slot {} holds a foreign object not created from Rust, expected {}", slot, expected)
+			}
+			SlotError::UnknownClass { module, class } => {
+				write!(f, "module `{}` has no class `{}`", module, class)
+			}
+		}
+	}
+}
+
+impl Error for SlotError {}
+
+/// Checked access to the slots of a VM.
+///
+/// Wren's slot functions only check their arguments with assertions, which
+/// are compiled out of release builds. Every method here checks the slot
+/// count and the type of the value first, so a mistake is an error instead of
+/// memory corruption.
+pub struct Slots<'vm> {
+	vm: *mut WrenVM,
+	_vm: PhantomData<&'vm mut WrenVM>,
+}
+
+impl<'vm> Slots<'vm> {
+	/// Wraps the slots of a raw VM, e.g. inside a foreign method.
+	///
+	/// # Safety
+	///
+	/// `vm` must be a live VM created by [`VmBuilder`](super::VmBuilder), and
+	/// must not be used through anything else while the returned value exists.
+	pub unsafe fn from_raw(vm: *mut WrenVM) -> Self {
+		Slots { vm, _vm: PhantomData }
+	}
+
+	/// Returns the raw VM pointer.
+	pub fn as_ptr(&self) -> *mut WrenVM {
+		self.vm
+	}
+
+	/// Returns the number of slots available.
+	pub fn count(&self) -> usize {
+		unsafe { wrenGetSlotCount(self.vm) as usize }
+	}
+
+	/// Makes sure at least `count` slots are available, growing the stack if
+	/// needed. Never shrinks it.
+	pub fn ensure(&mut self, count: usize) -> Result<(), SlotError> {
+		if count > c_int::MAX as usize {
+			return Err(SlotError::TooManySlots { count });
+		}
+		unsafe { wrenEnsureSlots(self.vm, count as c_int) };
+		Ok(())
+	}
+
+	fn check(&self, slot: usize) -> Result<c_int, SlotError> {
+		let count = self.count();
+		if slot < count {
+			Ok(slot as c_int)
+		} else {
+			Err(SlotError::OutOfBounds { slot, count })
+		}
+	}
+
+	fn check_type(&self, slot: usize, expected: WrenType) -> Result<c_int, SlotError> {
+		let found = self.slot_type(slot)?;
+		if found == expected {
+			Ok(slot as c_int)
+		} else {
+			Err(SlotError::Type { slot, expected, found })
+		}
+	}
+
+	// Classes and ranges are valid keys too, but they're `Unknown` like the
+	// objects that can't be hashed, so they're turned away with them.
+	fn check_key(&self, slot: usize) -> Result<c_int, SlotError> {
+		match self.slot_type(slot)? {
+			WrenType::Bool | WrenType::Num | WrenType::Null | WrenType::String => Ok(slot as c_int),
+			found => Err(SlotError::InvalidKey { slot, found }),
+		}
+	}
+
+	/// Returns the type of the value in `slot`.
+	pub fn slot_type(&self, slot: usize) -> Result<WrenType, SlotError> {
+		let slot = self.check(slot)?;
+		Ok(unsafe { wrenGetSlotType(self.vm, slot) })
+	}
+
+	pub fn get_bool(&self, slot: usize) -> Result<bool, SlotError> {
+		let slot = self.check_type(slot, WrenType::Bool)?;
+		Ok(unsafe { wrenGetSlotBool(self.vm, slot) })
+	}
+
+	pub fn get_double(&self, slot: usize) -> Result<f64, SlotError> {
+		let slot = self.check_type(slot, WrenType::Num)?;
+		Ok(unsafe { wrenGetSlotDouble(self.vm, slot) })
+	}
+
+	/// Reads the bytes of the string in `slot`. The bytes are owned by Wren and
+	/// can't be held on to past the next change to the slots.
+	pub fn get_bytes(&self, slot: usize) -> Result<&[u8], SlotError> {
+		let slot = self.check_type(slot, WrenType::String)?;
+		unsafe {
+			let mut length: c_int = 0;
+			let bytes = wrenGetSlotBytes(self.vm, slot, &mut length);
+			Ok(slice::from_raw_parts(bytes as *const u8, length as usize))
+		}
+	}
+
+	/// Reads the string in `slot`, which must be valid UTF-8.
+	pub fn get_str(&self, slot: usize) -> Result<&str, SlotError> {
+		let bytes = self.get_bytes(slot)?;
+		str::from_utf8(bytes).map_err(|_| SlotError::Utf8 { slot })
+	}
+
+	/// Returns a pointer to the data of the foreign object in `slot`.
+	pub fn get_foreign_ptr(&self, slot: usize) -> Result<*mut c_void, SlotError> {
+		let slot = self.check_type(slot, WrenType::Foreign)?;
+		Ok(unsafe { wrenGetSlotForeign(self.vm, slot) })
+	}
+
+	/// Creates a handle to the value in `slot`, keeping it alive until the
+	/// handle is dropped.
+	pub fn get_handle(&self, slot: usize) -> Result<Handle, SlotError> {
+		let slot = self.check(slot)?;
+		unsafe {
+			let raw = Context::get(self.vm).this.upgrade().expect("VM is being freed");
+			let handle = wrenGetSlotHandle(self.vm, slot);
+			Ok(Handle::from_raw(raw, handle))
+		}
+	}
+
+	pub fn set_bool(&mut self, slot: usize, value: bool) -> Result<(), SlotError> {
+		let slot = self.check(slot)?;
+		unsafe { wrenSetSlotBool(self.vm, slot, value) };
+		Ok(())
+	}
+
+	pub fn set_double(&mut self, slot: usize, value: f64) -> Result<(), SlotError> {
+		let slot = self.check(slot)?;
+		unsafe { wrenSetSlotDouble(self.vm, slot, value) };
+		Ok(())
+	}
+
+	/// Stores a copy of `bytes` as a string in `slot`.
+	pub fn set_bytes(&mut self, slot: usize, bytes: &[u8]) -> Result<(), SlotError> {
+		let slot = self.check(slot)?;
+		unsafe { wrenSetSlotBytes(self.vm, slot, bytes.as_ptr() as *const c_char, bytes.len()) };
+		Ok(())
+	}
+
+	/// Stores a copy of `text` in `slot`. Unlike `wrenSetSlotString`, `text`
+	/// may contain null bytes.
+	pub fn set_str(&mut self, slot: usize, text: &str) -> Result<(), SlotError> {
+		self.set_bytes(slot, text.as_bytes())
+	}
+
+	/// Stores the value `handle` refers to in `slot`.
+	pub fn set_handle(&mut self, slot: usize, handle: &Handle) -> Result<(), SlotError> {
+		if handle.vm() != self.vm {
+			return Err(SlotError::WrongVm { slot });
+		}
+		let slot = self.check(slot)?;
+		unsafe { wrenSetSlotHandle(self.vm, slot, handle.as_ptr()) };
+		Ok(())
+	}
+
+	pub fn set_null(&mut self, slot: usize) -> Result<(), SlotError> {
+		let slot = self.check(slot)?;
+		unsafe { wrenSetSlotNull(self.vm, slot) };
+		Ok(())
+	}
+
+	/// Stores a new empty list in `slot`.
+	pub fn set_new_list(&mut self, slot: usize) -> Result<(), SlotError> {
+		let slot = self.check(slot)?;
+		unsafe { wrenSetSlotNewList(self.vm, slot) };
+		Ok(())
+	}
+
+	/// Stores a new empty map in `slot`.
+	pub fn set_new_map(&mut self, slot: usize) -> Result<(), SlotError> {
+		let slot = self.check(slot)?;
+		unsafe { wrenSetSlotNewMap(self.vm, slot) };
+		Ok(())
+	}
+
+	/// Returns the number of elements in the list in `slot`.
+	pub fn list_count(&self, slot: usize) -> Result<usize, SlotError> {
+		let slot = self.check_type(slot, WrenType::List)?;
+		Ok(unsafe { wrenGetListCount(self.vm, slot) as usize })
+	}
+
+	// Checks [index] against the list in [list_slot]. Negative indexes count
+	// back from the end, as in Wren. Inserting allows one past the end.
+	fn check_index(&self, list_slot: usize, index: isize, insert: bool) -> Result<c_int, SlotError> {
+		let count = self.list_count(list_slot)?;
+		let len = count as isize + insert as isize;
+
+		if index >= -len && index < len {
+			Ok(index as c_int)
+		} else {
+			Err(SlotError::IndexOutOfBounds { slot: list_slot, index, count })
+		}
+	}
+
+	/// Reads element `index` of the list in `list_slot` into `element_slot`.
+	pub fn get_list_element(&mut self, list_slot: usize, index: isize, element_slot: usize) -> Result<(), SlotError> {
+		let index = self.check_index(list_slot, index, false)?;
+		let element_slot = self.check(element_slot)?;
+		unsafe { wrenGetListElement(self.vm, list_slot as c_int, index, element_slot) };
+		Ok(())
+	}
+
+	/// Replaces element `index` of the list in `list_slot` with the value in
+	/// `element_slot`.
+	pub fn set_list_element(&mut self, list_slot: usize, index: isize, element_slot: usize) -> Result<(), SlotError> {
+		let index = self.check_index(list_slot, index, false)?;
+		let element_slot = self.check(element_slot)?;
+		unsafe { wrenSetListElement(self.vm, list_slot as c_int, index, element_slot) };
+		Ok(())
+	}
+
+	/// Inserts the value in `element_slot` into the list in `list_slot` at
+	/// `index`. Use `-1` to append.
+	pub fn insert_in_list(&mut self, list_slot: usize, index: isize, element_slot: usize) -> Result<(), SlotError> {
+		let index = self.check_index(list_slot, index, true)?;
+		let element_slot = self.check(element_slot)?;
+		unsafe { wrenInsertInList(self.vm, list_slot as c_int, index, element_slot) };
+		Ok(())
+	}
+
+	/// Returns the number of entries in the map in `slot`.
+	pub fn map_count(&self, slot: usize) -> Result<usize, SlotError> {
+		let slot = self.check_type(slot, WrenType::Map)?;
+		Ok(unsafe { wrenGetMapCount(self.vm, slot) as usize })
+	}
+
+	/// Returns whether the map in `map_slot` has the key in `key_slot`.
+	pub fn map_contains_key(&self, map_slot: usize, key_slot: usize) -> Result<bool, SlotError> {
+		let map_slot = self.check_type(map_slot, WrenType::Map)?;
+		let key_slot = self.check_key(key_slot)?;
+		Ok(unsafe { wrenGetMapContainsKey(self.vm, map_slot, key_slot) })
+	}
+
+	/// Reads the value for the key in `key_slot` from the map in `map_slot`
+	/// into `value_slot`. Missing keys read as null.
+	pub fn get_map_value(&mut self, map_slot: usize, key_slot: usize, value_slot: usize) -> Result<(), SlotError> {
+		let map_slot = self.check_type(map_slot, WrenType::Map)?;
+		let key_slot = self.check_key(key_slot)?;
+		let value_slot = self.check(value_slot)?;
+		unsafe { wrenGetMapValue(self.vm, map_slot, key_slot, value_slot) };
+		Ok(())
+	}
+
+	/// Stores the value in `value_slot` under the key in `key_slot` in the map
+	/// in `map_slot`.
+	pub fn set_map_value(&mut self, map_slot: usize, key_slot: usize, value_slot: usize) -> Result<(), SlotError> {
+		let map_slot = self.check_type(map_slot, WrenType::Map)?;
+		let key_slot = self.check_key(key_slot)?;
+		let value_slot = self.check(value_slot)?;
+		unsafe { wrenSetMapValue(self.vm, map_slot, key_slot, value_slot) };
+		Ok(())
+	}
+
+	/// Removes the key in `key_slot` from the map in `map_slot`, storing the
+	/// removed value (or null) in `removed_slot`.
+	pub fn remove_map_value(&mut self, map_slot: usize, key_slot: usize, removed_slot: usize) -> Result<(), SlotError> {
+		let map_slot = self.check_type(map_slot, WrenType::Map)?;
+		let key_slot = self.check_key(key_slot)?;
+		let removed_slot = self.check(removed_slot)?;
+		unsafe { wrenRemoveMapValue(self.vm, map_slot, key_slot, removed_slot) };
+		Ok(())
+	}
+
+	/// Aborts the current fiber, using the value in `slot` as the error.
+	pub fn abort_fiber(&mut self, slot: usize) -> Result<(), SlotError> {
+		let slot = self.check(slot)?;
+		unsafe { wrenAbortFiber(self.vm, slot) };
+		Ok(())
+	}
+}
@@ -0,0 +1,167 @@
+use std::ffi::CString;
+use std::rc::Rc;
+
+use super::call::signature_arity;
+use super::context::Context;
+use super::{FromWren, Handle, Slots, VmBuilder, WrenError};
+use crate::*;
+
+// The VM itself, shared between a `Vm` and the `Handle`s created from it so
+// that it is only freed once all of them are gone.
+pub(crate) struct Raw {
+	pub vm: *mut WrenVM,
+	context: *mut Context,
+}
+
+impl Raw {
+	// The returned reference must not be held across calls into Wren, which
+	// may use the context from a callback.
+	#[allow(clippy::mut_from_ref)]
+	pub unsafe fn context(&self) -> &mut Context {
+		&mut *self.context
+	}
+}
+
+impl Drop for Raw {
+	fn drop(&mut self) {
+		unsafe {
+			// Wren expects every handle to be released before it is freed.
+			for (_, handle) in self.context().call_handles.drain() {
+				wrenReleaseHandle(self.vm, handle);
+			}
+			wrenFreeVM(self.vm);
+			drop(Box::from_raw(self.context));
+		}
+	}
+}
+
+/// An owned Wren virtual machine.
+///
+/// The VM is freed with `wrenFreeVM` once this and every [`Handle`] created
+/// from it have been dropped. Use [`VmBuilder`] to configure it.
+pub struct Vm {
+	raw: Rc<Raw>,
+}
+
+impl Vm {
+	/// Creates a new VM with the default configuration.
+	pub fn new() -> Self {
+		VmBuilder::new().build()
+	}
+
+	pub(crate) unsafe fn from_raw(vm: *mut WrenVM, context: *mut Context) -> Self {
+		let raw = Rc::new_cyclic(|this| {
+			(*context).this = this.clone();
+			Raw { vm, context }
+		});
+		Vm { raw }
+	}
+
+	/// Runs `source` in a new fiber, in the context of the resolved `module`.
+	pub fn interpret(&mut self, module: &str, source: &str) -> Result<(), WrenError> {
+		let module = CString::new(module)?;
+		let source = CString::new(source)?;
+
+		let result = unsafe { wrenInterpret(self.raw.vm, module.as_ptr(), source.as_ptr()) };
+
+		let context = self.context();
+		let result = context.errors.finish(result);
+		context.resume_panic();
+		result
+	}
+
+	/// Gives checked access to the VM's slots, e.g. to set up values before
+	/// calling into Wren or to read results afterwards.
+	pub fn slots(&mut self) -> Slots<'_> {
+		unsafe { Slots::from_raw(self.raw.vm) }
+	}
+
+	/// Creates a handle for calling the method with `signature` on any
+	/// receiver, e.g. `"update(_,_)"`.
+	pub fn make_call_handle(&mut self, signature: &str) -> Result<Handle, WrenError> {
+		// Wren only checks the signature with debug assertions, and reads past
+		// the end of an empty one.
+		if signature_arity(signature).is_none() {
+			return Err(WrenError::Signature(signature.to_string()));
+		}
+		let signature = CString::new(signature)?;
+		unsafe {
+			let handle = wrenMakeCallHandle(self.raw.vm, signature.as_ptr());
+			Ok(Handle::from_raw(self.raw.clone(), handle))
+		}
+	}
+
+	/// Returns whether the resolved `module` has been imported or interpreted.
+	pub fn has_module(&mut self, module: &str) -> bool {
+		match CString::new(module) {
+			Ok(module) => unsafe { wrenHasModule(self.raw.vm, module.as_ptr()) },
+			Err(_) => false,
+		}
+	}
+
+	/// Returns whether the resolved `module` has a top-level variable `name`.
+	pub fn has_variable(&mut self, module: &str, name: &str) -> bool {
+		let (module, name) = match (CString::new(module), CString::new(name)) {
+			(Ok(module), Ok(name)) => (module, name),
+			_ => return false,
+		};
+		unsafe {
+			// Wren asserts the module exists before looking in it.
+			wrenHasModule(self.raw.vm, module.as_ptr()) && wrenHasVariable(self.raw.vm, module.as_ptr(), name.as_ptr())
+		}
+	}
+
+	/// Reads the top-level variable `name` in the resolved `module`.
+	///
+	/// Fetching a class as a [`Handle`] is how to call its static methods:
+	///
+	/// ```
+	/// # use wren_sys::wren::{Handle, Vm};
+	/// # let mut vm = Vm::new();
+	/// # vm.interpret("main", "class Game {\n\tstatic tick() {}\n}")?;
+	/// let game: Handle = vm.get_variable("main", "Game")?;
+	/// vm.call::<(), _, _>(&game, "tick()", ())?;
+	/// # Ok::<(), wren_sys::wren::WrenError>(())
+	/// ```
+	pub fn get_variable<T: FromWren>(&mut self, module: &str, name: &str) -> Result<T, WrenError> {
+		if !self.has_module(module) {
+			return Err(WrenError::UnknownModule(module.to_string()));
+		}
+		if !self.has_variable(module, name) {
+			return Err(WrenError::UnknownVariable {
+				module: module.to_string(),
+				name: name.to_string(),
+			});
+		}
+
+		let c_module = CString::new(module)?;
+		let c_name = CString::new(name)?;
+		let mut slots = self.slots();
+		slots.ensure(1)?;
+		unsafe { wrenGetVariable(slots.as_ptr(), c_module.as_ptr(), c_name.as_ptr(), 0) };
+		Ok(slots.get(0)?)
+	}
+
+	/// Immediately runs the garbage collector to free unused memory.
+	pub fn collect_garbage(&mut self) {
+		unsafe { wrenCollectGarbage(self.raw.vm) }
+	}
+
+	/// Returns the raw VM pointer, for use with the `extern "C"` functions.
+	///
+	/// The pointer is only valid for as long as this `Vm` is alive, and its
+	/// user data belongs to this crate.
+	pub fn as_ptr(&self) -> *mut WrenVM {
+		self.raw.vm
+	}
+
+	pub(crate) fn context(&mut self) -> &mut Context {
+		unsafe { self.raw.context() }
+	}
+}
+
+impl Default for Vm {
+	fn default() -> Self {
+		Vm::new()
+	}
+}
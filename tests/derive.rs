@@ -0,0 +1,151 @@
+// Checks the code generated for a derived class: the Wren declarations it
+// produces, and the methods it binds in a real VM. Also checks the modules
+// `include_wren_dir!` embeds.
+#![cfg(feature = "derive")]
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use wren_sys::wren::{include_wren_dir, wren_methods, ForeignRegistry, VmBuilder, WrenClass, WrenError, WrenMethods};
+
+#[derive(Clone, WrenClass)]
+#[wren(module = "vector")]
+struct Vec3 {
+	x: f64,
+	y: f64,
+	z: f64,
+}
+
+#[wren_methods]
+impl Vec3 {
+	#[wren(construct)]
+	fn new(x: f64, y: f64, z: f64) -> Self {
+		Vec3 { x, y, z }
+	}
+
+	#[wren(construct)]
+	fn splat(value: f64) -> Self {
+		Vec3::new(value, value, value)
+	}
+
+	#[wren(getter)]
+	fn zero() -> Self {
+		Vec3::splat(0.0)
+	}
+
+	#[wren(getter)]
+	fn x(&self) -> f64 {
+		self.x
+	}
+
+	#[wren(setter)]
+	fn set_x(&mut self, x: f64) {
+		self.x = x;
+	}
+
+	fn dot(&self, other: &Vec3) -> f64 {
+		self.x * other.x + self.y * other.y + self.z * other.z
+	}
+
+	#[wren(operator = "+")]
+	fn add(&self, other: &Vec3) -> Vec3 {
+		Vec3::new(self.x + other.x, self.y + other.y, self.z + other.z)
+	}
+
+	#[wren(operator = "-")]
+	fn neg(&self) -> Self {
+		Vec3::new(-self.x, -self.y, -self.z)
+	}
+
+	#[wren(index)]
+	fn component(&self, index: usize) -> Result<f64, String> {
+		[self.x, self.y, self.z].get(index).copied().ok_or_else(|| format!("no component {}", index))
+	}
+
+	#[wren(getter, name = "toString")]
+	fn describe(&self) -> String {
+		format!("({}, {}, {})", self.x, self.y, self.z)
+	}
+
+	#[wren(skip)]
+	#[allow(dead_code)]
+	fn scale(&self, factor: f64) -> Vec3 {
+		Vec3::new(self.x * factor, self.y * factor, self.z * factor)
+	}
+}
+
+#[derive(WrenClass)]
+#[wren(module = "counter", name = "Counter")]
+struct Count;
+
+#[test]
+fn class_names() {
+	assert_eq!(Vec3::MODULE, "vector");
+	assert_eq!(Vec3::NAME, "Vec3");
+	assert_eq!(Count::MODULE, "counter");
+	assert_eq!(Count::NAME, "Counter");
+}
+
+#[test]
+fn declaration() {
+	assert_eq!(
+		Vec3::source(),
+		"foreign class Vec3 {\n\
+		 \tconstruct new(a0, a1, a2) {}\n\
+		 \tconstruct splat(a0) {}\n\
+		 \tforeign +(a0)\n\
+		 \tforeign -\n\
+		 \tforeign [a0]\n\
+		 \tforeign dot(a0)\n\
+		 \tforeign toString\n\
+		 \tforeign x\n\
+		 \tforeign x=(a0)\n\
+		 \tforeign static zero\n\
+		 }\n"
+	);
+}
+
+#[test]
+fn derived_class() {
+	let mut registry = ForeignRegistry::new();
+	registry.register::<Vec3>();
+	let output = Rc::new(RefCell::new(String::new()));
+	let written = output.clone();
+	let mut vm = VmBuilder::new()
+		.foreign(registry)
+		.write(move |text| written.borrow_mut().push_str(text))
+		.build();
+
+	// Each constructor gets its own allocator arm, picked by arity; `zero`
+	// and `-` return new instances; `+` and `dot` clone their argument.
+	let source = "import \"vector\" for Vec3\n\
+		var a = Vec3.new(1, 2, 3)\n\
+		var b = Vec3.splat(2)\n\
+		a.x = 4\n\
+		System.print(a + b + Vec3.zero)\n\
+		System.print(-b)\n\
+		System.print([a.x, a.dot(b), a[1], b[2]])\n";
+	vm.interpret("main", source).unwrap();
+	assert_eq!(*output.borrow(), "(6, 4, 5)\n(-2, -2, -2)\n[4, 18, 2, 2]\n");
+
+	match vm.interpret("main", "import \"vector\" for Vec3\nVec3.new(1, 2, 3)[3]") {
+		Err(WrenError::Runtime { message, .. }) => assert_eq!(message, "no component 3"),
+		other => panic!("unexpected {:?}", other),
+	}
+	match vm.interpret("main", "import \"vector\" for Vec3\nVec3.splat(1) + 1") {
+		Err(WrenError::Runtime { .. }) => {}
+		other => panic!("unexpected {:?}", other),
+	}
+}
+
+#[test]
+fn included_dir() {
+	let scripts = include_wren_dir!("tests/fixtures/scripts");
+	let mut names: Vec<_> = scripts.iter().map(|(name, _)| name).collect();
+	names.sort_unstable();
+	assert_eq!(names, ["lib/util", "lib/vector", "main"]);
+
+	let sources: Vec<_> = scripts.iter().collect();
+	let vector = sources.iter().find(|(name, _)| *name == "lib/vector").unwrap().1;
+	assert_eq!(vector, include_str!("fixtures/scripts/lib/vector.wren"));
+}
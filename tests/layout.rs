@@ -0,0 +1,156 @@
+// Checks that the hand-written `#[repr(C)]` declarations in `src/lib.rs` have
+// the same size, alignment and field offsets as the types in `wren.h`. The
+// expected values are worked out from the C declarations for the target's
+// pointer width.
+
+use std::mem::{align_of, offset_of, size_of};
+
+use libc::{c_int, size_t};
+use wren_sys::*;
+
+const PTR: usize = size_of::<*const u8>();
+
+#[test]
+fn wren_configuration() {
+	assert_eq!(size_of::<WrenConfiguration>(), 11 * PTR);
+	assert_eq!(align_of::<WrenConfiguration>(), PTR);
+
+	assert_eq!(offset_of!(WrenConfiguration, reallocate_fn), 0);
+	assert_eq!(offset_of!(WrenConfiguration, resolve_module_fn), PTR);
+	assert_eq!(offset_of!(WrenConfiguration, load_module_fn), 2 * PTR);
+	assert_eq!(offset_of!(WrenConfiguration, bind_foreign_method_fn), 3 * PTR);
+	assert_eq!(offset_of!(WrenConfiguration, bind_foreign_class_fn), 4 * PTR);
+	assert_eq!(offset_of!(WrenConfiguration, write_fn), 5 * PTR);
+	assert_eq!(offset_of!(WrenConfiguration, error_fn), 6 * PTR);
+	assert_eq!(offset_of!(WrenConfiguration, initial_heap_size), 7 * PTR);
+	assert_eq!(offset_of!(WrenConfiguration, min_heap_size), 7 * PTR + size_of::<size_t>());
+	assert_eq!(offset_of!(WrenConfiguration, heap_growth_percent), 7 * PTR + 2 * size_of::<size_t>());
+
+	// `int heapGrowthPercent` is padded up to the alignment of `void* userData`.
+	assert_eq!(offset_of!(WrenConfiguration, user_data), 10 * PTR);
+}
+
+#[test]
+fn wren_foreign_class_methods() {
+	assert_eq!(size_of::<WrenForeignClassMethods>(), 2 * PTR);
+	assert_eq!(align_of::<WrenForeignClassMethods>(), PTR);
+
+	assert_eq!(offset_of!(WrenForeignClassMethods, allocate), 0);
+	assert_eq!(offset_of!(WrenForeignClassMethods, finalize), PTR);
+}
+
+#[test]
+fn wren_load_module_result() {
+	assert_eq!(size_of::<WrenLoadModuleResult>(), 3 * PTR);
+	assert_eq!(align_of::<WrenLoadModuleResult>(), PTR);
+
+	assert_eq!(offset_of!(WrenLoadModuleResult, source), 0);
+	assert_eq!(offset_of!(WrenLoadModuleResult, on_complete), PTR);
+	assert_eq!(offset_of!(WrenLoadModuleResult, user_data), 2 * PTR);
+}
+
+#[test]
+fn nullable_callbacks() {
+	// `Option` of a function pointer uses NULL for `None`, so it has the same
+	// layout as the C function pointer.
+	assert_eq!(size_of::<Option<WrenForeignMethodFn>>(), PTR);
+	assert_eq!(size_of::<Option<WrenFinalizerFn>>(), PTR);
+	assert_eq!(size_of::<Option<WrenReallocateFn>>(), PTR);
+	assert_eq!(size_of::<Option<WrenLoadModuleFn>>(), PTR);
+	assert_eq!(size_of::<Option<WrenErrorFn>>(), PTR);
+}
+
+#[test]
+fn wren_enums() {
+	// C enums without explicit values are `int` sized and numbered from zero.
+	assert_eq!(size_of::<WrenErrorType>(), size_of::<c_int>());
+	assert_eq!(WrenErrorType::Compile as c_int, 0);
+	assert_eq!(WrenErrorType::Runtime as c_int, 1);
+	assert_eq!(WrenErrorType::StackTrace as c_int, 2);
+
+	assert_eq!(size_of::<WrenInterpretResult>(), size_of::<c_int>());
+	assert_eq!(WrenInterpretResult::Success as c_int, 0);
+	assert_eq!(WrenInterpretResult::CompileError as c_int, 1);
+	assert_eq!(WrenInterpretResult::RuntimeError as c_int, 2);
+
+	assert_eq!(size_of::<WrenType>(), size_of::<c_int>());
+	assert_eq!(WrenType::Bool as c_int, 0);
+	assert_eq!(WrenType::Num as c_int, 1);
+	assert_eq!(WrenType::Foreign as c_int, 2);
+	assert_eq!(WrenType::List as c_int, 3);
+	assert_eq!(WrenType::Map as c_int, 4);
+	assert_eq!(WrenType::Null as c_int, 5);
+	assert_eq!(WrenType::String as c_int, 6);
+	assert_eq!(WrenType::Unknown as c_int, 7);
+}
+
+#[test]
+fn wren_version() {
+	// The version of the Wren that was built or linked against, as read by the
+	// build script from `wren.h` or pkg-config.
+	let found = env!("WREN_FOUND_VERSION");
+	assert_eq!(WREN_VERSION_STRING, found);
+	assert_eq!(
+		format!("{}.{}.{}", WREN_VERSION_MAJOR, WREN_VERSION_MINOR, WREN_VERSION_PATCH),
+		found
+	);
+}
+
+// With the `bindgen` feature the header is available as Rust, so compare the
+// hand-written declarations against it directly.
+#[cfg(feature = "bindgen")]
+mod generated {
+	use std::mem::{align_of, offset_of, size_of};
+	use wren_sys::bindings;
+
+	macro_rules! assert_same_layout {
+		($ours:ty, $theirs:ty, { $($field:ident => $c_field:ident),* $(,)? }) => {
+			assert_eq!(size_of::<$ours>(), size_of::<$theirs>());
+			assert_eq!(align_of::<$ours>(), align_of::<$theirs>());
+			$(assert_eq!(offset_of!($ours, $field), offset_of!($theirs, $c_field));)*
+		};
+	}
+
+	#[test]
+	fn structs() {
+		assert_same_layout!(wren_sys::WrenConfiguration, bindings::WrenConfiguration, {
+			reallocate_fn => reallocateFn,
+			resolve_module_fn => resolveModuleFn,
+			load_module_fn => loadModuleFn,
+			bind_foreign_method_fn => bindForeignMethodFn,
+			bind_foreign_class_fn => bindForeignClassFn,
+			write_fn => writeFn,
+			error_fn => errorFn,
+			initial_heap_size => initialHeapSize,
+			min_heap_size => minHeapSize,
+			heap_growth_percent => heapGrowthPercent,
+			user_data => userData,
+		});
+		assert_same_layout!(wren_sys::WrenForeignClassMethods, bindings::WrenForeignClassMethods, {
+			allocate => allocate,
+			finalize => finalize,
+		});
+		assert_same_layout!(wren_sys::WrenLoadModuleResult, bindings::WrenLoadModuleResult, {
+			source => source,
+			on_complete => onComplete,
+			user_data => userData,
+		});
+	}
+
+	#[test]
+	fn enums() {
+		assert_eq!(size_of::<wren_sys::WrenErrorType>(), size_of::<bindings::WrenErrorType>());
+		assert_eq!(size_of::<wren_sys::WrenInterpretResult>(), size_of::<bindings::WrenInterpretResult>());
+		assert_eq!(size_of::<wren_sys::WrenType>(), size_of::<bindings::WrenType>());
+
+		assert_eq!(wren_sys::WrenType::Map as i64, bindings::WrenType_WREN_TYPE_MAP as i64);
+		assert_eq!(wren_sys::WrenType::Unknown as i64, bindings::WrenType_WREN_TYPE_UNKNOWN as i64);
+		assert_eq!(wren_sys::WrenErrorType::StackTrace as i64, bindings::WrenErrorType_WREN_ERROR_STACK_TRACE as i64);
+		assert_eq!(wren_sys::WrenInterpretResult::RuntimeError as i64, bindings::WrenInterpretResult_WREN_RESULT_RUNTIME_ERROR as i64);
+	}
+
+	#[test]
+	fn version() {
+		assert_eq!(wren_sys::WREN_VERSION_NUMBER as i64, bindings::WREN_VERSION_NUMBER as i64);
+	}
+}
@@ -0,0 +1,710 @@
+// Runs scripts in a real VM, built from the vendored sources or the libwren
+// linked with `WREN_LIB_DIR`.
+
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
+
+use wren_sys::wren::*;
+use wren_sys::{WrenErrorType, WrenType};
+
+// A VM whose output is collected into the returned string.
+fn vm_with(builder: VmBuilder) -> (Vm, Rc<RefCell<String>>) {
+	let output = Rc::new(RefCell::new(String::new()));
+	let written = output.clone();
+	let vm = builder.write(move |text| written.borrow_mut().push_str(text)).build();
+	(vm, output)
+}
+
+#[test]
+fn interpret() {
+	let mut vm = Vm::new();
+	vm.interpret("main", "var answer = 6 * 7").unwrap();
+	vm.collect_garbage();
+
+	assert!(matches!(vm.interpret("main", "var x = "), Err(WrenError::Compile(_))));
+	assert!(matches!(vm.interpret("main", "Fiber.abort(\"boom\")"), Err(WrenError::Runtime { .. })));
+	assert!(matches!(vm.interpret("main", "var x = 1\0"), Err(WrenError::Nul(_))));
+
+	// The VM is still usable after an error.
+	vm.interpret("main", "var y = answer").unwrap();
+}
+
+#[test]
+fn write() {
+	let (mut vm, output) = vm_with(VmBuilder::new().initial_heap_size(1024 * 1024));
+	vm.interpret("main", "System.print(\"Hello, %(1 + 2)!\")").unwrap();
+	assert_eq!(*output.borrow(), "Hello, 3!\n");
+}
+
+#[test]
+fn error_events() {
+	let events = Rc::new(RefCell::new(Vec::new()));
+	let reported = events.clone();
+	let mut vm = VmBuilder::new().error(move |event| reported.borrow_mut().push(event)).build();
+
+	assert!(vm.interpret("main", "var x = 1\nvar y = ").is_err());
+	{
+		let events = events.borrow();
+		assert_eq!(events[0].kind, WrenErrorType::Compile);
+		assert_eq!((events[0].module.as_deref(), events[0].line), (Some("main"), Some(2)));
+	}
+
+	events.borrow_mut().clear();
+	assert!(vm.interpret("main", "Fiber.abort(\"boom\")").is_err());
+	let events = events.borrow();
+	assert_eq!(events.len(), 2);
+	assert_eq!((events[0].kind, events[0].message.as_str()), (WrenErrorType::Runtime, "boom"));
+	assert_eq!(events[0].module, None);
+	assert_eq!(events[1].kind, WrenErrorType::StackTrace);
+	assert_eq!(events[1].message, "(script)");
+}
+
+#[test]
+fn write_panics() {
+	let mut vm = VmBuilder::new().write(|_| panic!("from write")).build();
+	let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| vm.interpret("main", "System.print(1)")));
+	assert_eq!(result.unwrap_err().downcast_ref::<&str>(), Some(&"from write"));
+}
+
+#[test]
+fn compile_error() {
+	let mut vm = Vm::new();
+	match vm.interpret("main", "var x = 1\nvar y = ") {
+		Err(WrenError::Compile(diagnostics)) => {
+			assert!(!diagnostics.is_empty());
+			assert_eq!(diagnostics[0].module, "main");
+			assert_eq!(diagnostics[0].line, 2);
+		}
+		other => panic!("unexpected {:?}", other),
+	}
+}
+
+#[test]
+fn runtime_error() {
+	let mut vm = Vm::new();
+	let source = "class Game {\n\tstatic fail() {\n\t\tFiber.abort(\"boom\")\n\t}\n}\nGame.fail()\n";
+	match vm.interpret("main", source) {
+		Err(WrenError::Runtime { message, stack }) => {
+			assert_eq!(message, "boom");
+			assert_eq!(stack.len(), 2);
+			assert_eq!((stack[0].module.as_str(), stack[0].line), ("main", 3));
+			assert!(stack[0].function.contains("fail()"));
+			assert_eq!((stack[1].line, stack[1].function.as_str()), (6, "(script)"));
+		}
+		other => panic!("unexpected {:?}", other),
+	}
+
+	// The next error doesn't include this one.
+	match vm.interpret("main", "null.foo()") {
+		Err(WrenError::Runtime { stack, .. }) => assert_eq!(stack.len(), 1),
+		other => panic!("unexpected {:?}", other),
+	}
+}
+
+#[test]
+fn slots() {
+	let mut vm = Vm::new();
+	let mut slots = vm.slots();
+	slots.ensure(4).unwrap();
+	let count = slots.count();
+	assert!(count >= 4);
+
+	slots.set_double(0, 1.5).unwrap();
+	slots.set_str(1, "caf\u{e9}").unwrap();
+	slots.set_bytes(2, b"\xff\0").unwrap();
+	slots.set_bool(3, true).unwrap();
+	assert_eq!(slots.slot_type(0).unwrap(), WrenType::Num);
+	assert_eq!(slots.get_double(0).unwrap(), 1.5);
+	assert_eq!(slots.get_str(1).unwrap(), "caf\u{e9}");
+	assert_eq!(slots.get_bytes(2).unwrap(), b"\xff\0");
+	assert_eq!(slots.get_str(2), Err(SlotError::Utf8 { slot: 2 }));
+	assert!(slots.get_bool(3).unwrap());
+
+	slots.set_null(3).unwrap();
+	assert_eq!(slots.slot_type(3).unwrap(), WrenType::Null);
+	assert_eq!(
+		slots.get_double(3),
+		Err(SlotError::Type {
+			slot: 3,
+			expected: WrenType::Num,
+			found: WrenType::Null
+		})
+	);
+	assert_eq!(slots.set_null(count), Err(SlotError::OutOfBounds { slot: count, count }));
+	assert_eq!(slots.slot_type(count), Err(SlotError::OutOfBounds { slot: count, count }));
+
+	let too_many = i32::MAX as usize + 1;
+	assert_eq!(slots.ensure(too_many), Err(SlotError::TooManySlots { count: too_many }));
+	assert_eq!(slots.count(), count);
+}
+
+#[test]
+fn lists() {
+	let mut vm = Vm::new();
+	let mut slots = vm.slots();
+	slots.ensure(3).unwrap();
+	slots.set_new_list(0).unwrap();
+
+	// Appending with -1, then inserting one past the end.
+	for (index, value) in [(-1, 1.0), (-1, 2.0), (2, 3.0)] {
+		slots.set_double(1, value).unwrap();
+		slots.insert_in_list(0, index, 1).unwrap();
+	}
+	assert_eq!(slots.list_count(0).unwrap(), 3);
+	let out_of_bounds = |index| SlotError::IndexOutOfBounds { slot: 0, index, count: 3 };
+	assert_eq!(slots.insert_in_list(0, 4, 1), Err(out_of_bounds(4)));
+	assert_eq!(slots.insert_in_list(0, -5, 1), Err(out_of_bounds(-5)));
+
+	slots.get_list_element(0, -1, 2).unwrap();
+	assert_eq!(slots.get_double(2).unwrap(), 3.0);
+	slots.get_list_element(0, -3, 2).unwrap();
+	assert_eq!(slots.get_double(2).unwrap(), 1.0);
+	assert_eq!(slots.get_list_element(0, 3, 2), Err(out_of_bounds(3)));
+	assert_eq!(slots.get_list_element(0, -4, 2), Err(out_of_bounds(-4)));
+
+	slots.set_double(1, 9.0).unwrap();
+	slots.set_list_element(0, 1, 1).unwrap();
+	slots.set_list_element(0, -1, 1).unwrap();
+	slots.get_list_element(0, 1, 2).unwrap();
+	assert_eq!(slots.get_double(2).unwrap(), 9.0);
+	slots.get_list_element(0, 2, 2).unwrap();
+	assert_eq!(slots.get_double(2).unwrap(), 9.0);
+	assert_eq!(slots.set_list_element(0, 3, 1), Err(out_of_bounds(3)));
+	assert_eq!(slots.set_list_element(0, -4, 1), Err(out_of_bounds(-4)));
+	assert_eq!(slots.list_count(0).unwrap(), 3);
+
+	assert!(matches!(slots.list_count(1), Err(SlotError::Type { .. })));
+}
+
+#[test]
+fn maps() {
+	let mut vm = Vm::new();
+	let mut slots = vm.slots();
+	slots.ensure(4).unwrap();
+	slots.set_new_map(0).unwrap();
+
+	slots.set_str(1, "a").unwrap();
+	slots.set_double(2, 1.0).unwrap();
+	slots.set_map_value(0, 1, 2).unwrap();
+	assert_eq!(slots.map_count(0).unwrap(), 1);
+	assert!(slots.map_contains_key(0, 1).unwrap());
+	slots.get_map_value(0, 1, 3).unwrap();
+	assert_eq!(slots.get_double(3).unwrap(), 1.0);
+
+	slots.set_str(1, "b").unwrap();
+	assert!(!slots.map_contains_key(0, 1).unwrap());
+	slots.get_map_value(0, 1, 3).unwrap();
+	assert_eq!(slots.slot_type(3).unwrap(), WrenType::Null);
+
+	slots.set_str(1, "a").unwrap();
+	slots.remove_map_value(0, 1, 3).unwrap();
+	assert_eq!(slots.get_double(3).unwrap(), 1.0);
+	assert_eq!(slots.map_count(0).unwrap(), 0);
+	slots.remove_map_value(0, 1, 3).unwrap();
+	assert_eq!(slots.slot_type(3).unwrap(), WrenType::Null);
+
+	// Lists can't be hashed, so they aren't keys.
+	slots.set_new_list(1).unwrap();
+	let invalid = SlotError::InvalidKey { slot: 1, found: WrenType::List };
+	assert_eq!(slots.map_contains_key(0, 1), Err(invalid.clone()));
+	assert_eq!(slots.set_map_value(0, 1, 2), Err(invalid.clone()));
+	assert_eq!(slots.remove_map_value(0, 1, 3), Err(invalid));
+	assert!(matches!(slots.map_count(1), Err(SlotError::Type { .. })));
+}
+
+#[test]
+fn conversions() {
+	let mut vm = Vm::new();
+	let mut slots = vm.slots();
+	slots.ensure(5).unwrap();
+
+	slots.set(0, 1.5).unwrap();
+	slots.set(1, "caf\u{e9}\0!").unwrap();
+	slots.set(2, true).unwrap();
+	slots.set(3, None::<f64>).unwrap();
+	slots.set(4, vec![vec![1.0], vec![2.0, 3.0]]).unwrap();
+	assert_eq!(slots.get::<f64>(0).unwrap(), 1.5);
+	assert_eq!(slots.get::<f32>(0).unwrap(), 1.5);
+	assert_eq!(slots.get::<String>(1).unwrap(), "caf\u{e9}\0!");
+	assert!(slots.get::<bool>(2).unwrap());
+	assert_eq!(slots.get::<Option<f64>>(3).unwrap(), None);
+	assert_eq!(slots.get::<Option<f64>>(0).unwrap(), Some(1.5));
+	assert_eq!(slots.get::<Vec<Vec<f64>>>(4).unwrap(), vec![vec![1.0], vec![2.0, 3.0]]);
+	slots.get::<()>(4).unwrap();
+
+	// Bytes are strings, not lists of numbers.
+	slots.set(0, b"\xff\x00".to_vec()).unwrap();
+	assert_eq!(slots.slot_type(0).unwrap(), WrenType::String);
+	assert_eq!(slots.get::<Vec<u8>>(0).unwrap(), b"\xff\x00");
+	assert!(matches!(slots.get::<String>(0), Err(SlotError::Utf8 { slot: 0 })));
+	assert!(matches!(slots.get::<Vec<f64>>(0), Err(SlotError::Type { .. })));
+
+	let mut map = BTreeMap::new();
+	map.insert("a", 1.0);
+	map.insert("b", 2.0);
+	slots.set(0, &map).unwrap();
+	assert_eq!(slots.map_count(0).unwrap(), 2);
+	assert_eq!(slots.map_get::<_, f64>(0, "b").unwrap(), Some(2.0));
+	assert_eq!(slots.map_get::<_, f64>(0, "c").unwrap(), None);
+
+	let mut map = HashMap::new();
+	map.insert(1, vec!["one"]);
+	slots.set(0, map).unwrap();
+	assert_eq!(slots.map_get::<_, Vec<String>>(0, 1).unwrap(), Some(vec!["one".to_string()]));
+	assert!(matches!(slots.map_get::<_, f64>(0, 1), Err(SlotError::Type { .. })));
+}
+
+#[test]
+fn integers() {
+	let mut vm = Vm::new();
+	let mut slots = vm.slots();
+	slots.ensure(1).unwrap();
+
+	fn is_integer_error<T>(result: Result<T, SlotError>, expected: &str) -> bool {
+		matches!(result, Err(SlotError::Integer { slot: 0, target, .. }) if target == expected)
+	}
+
+	for value in [0.0, -0.0, 1.0, -128.0, 127.0] {
+		slots.set(0, value).unwrap();
+		assert_eq!(slots.get::<i8>(0).unwrap() as f64, value);
+	}
+	slots.set(0, 4_294_967_295.0).unwrap();
+	assert_eq!(slots.get::<u32>(0).unwrap(), u32::MAX);
+	assert_eq!(slots.get::<i64>(0).unwrap(), 4_294_967_295);
+
+	// Fractions, NaN and infinities aren't integers.
+	for value in [0.5, -1.25, f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+		slots.set(0, value).unwrap();
+		assert!(is_integer_error(slots.get::<i32>(0), "i32"), "{}", value);
+		assert!(is_integer_error(slots.get::<u64>(0), "u64"), "{}", value);
+	}
+
+	// Out of range for the type, rather than saturated or wrapped.
+	for value in [128.0, -129.0, 1e10] {
+		slots.set(0, value).unwrap();
+		assert!(is_integer_error(slots.get::<i8>(0), "i8"), "{}", value);
+	}
+	for value in [4_294_967_296.0, -1.0, 1e300] {
+		slots.set(0, value).unwrap();
+		assert!(is_integer_error(slots.get::<u32>(0), "u32"), "{}", value);
+	}
+	slots.set(0, -1.0).unwrap();
+	assert!(is_integer_error(slots.get::<u16>(0), "u16"));
+	assert!(is_integer_error(slots.get::<usize>(0), "usize"));
+	assert_eq!(slots.get::<isize>(0).unwrap(), -1);
+
+	// Integers only become numbers if the double holds them exactly.
+	slots.set(0, 1u64 << 53).unwrap();
+	assert_eq!(slots.get::<f64>(0).unwrap(), 9_007_199_254_740_992.0);
+	assert_eq!(slots.get::<u64>(0).unwrap(), 1 << 53);
+	assert!(is_integer_error(slots.set(0, (1u64 << 53) + 1), "f64"));
+	assert!(is_integer_error(slots.set(0, u64::MAX), "f64"));
+	assert!(is_integer_error(slots.set(0, i64::MIN + 1), "f64"));
+	slots.set(0, i64::MIN).unwrap();
+	assert_eq!(slots.get::<i64>(0).unwrap(), i64::MIN);
+	slots.set(0, u32::MAX).unwrap();
+	assert_eq!(slots.get::<f64>(0).unwrap(), 4_294_967_295.0);
+}
+
+#[test]
+fn handles() {
+	let mut vm = Vm::new();
+	let mut slots = vm.slots();
+	slots.ensure(2).unwrap();
+	slots.set(0, vec!["kept"]).unwrap();
+	let list: Handle = slots.get(0).unwrap();
+	slots.set_null(0).unwrap();
+
+	vm.collect_garbage();
+	let mut slots = vm.slots();
+	slots.set_handle(1, &list).unwrap();
+	assert_eq!(slots.get::<Vec<String>>(1).unwrap(), vec!["kept".to_string()]);
+	let method = vm.make_call_handle("count").unwrap();
+	for signature in ["", "(_)", "update(", "[]"] {
+		match vm.make_call_handle(signature) {
+			Err(WrenError::Signature(rejected)) => assert_eq!(rejected, signature),
+			other => panic!("unexpected {:?}", other.map(|_| ())),
+		}
+	}
+
+	// Handles keep their VM alive, and can't be stored in another one.
+	let mut other = Vm::new();
+	let mut slots = other.slots();
+	slots.ensure(1).unwrap();
+	assert_eq!(slots.set_handle(0, &list), Err(SlotError::WrongVm { slot: 0 }));
+	drop(vm);
+	drop(method);
+	drop(list);
+}
+
+#[test]
+fn call() {
+	let mut vm = Vm::new();
+
+	// Repeated calls reuse the call handle made by the first.
+	for i in 0..100 {
+		let sum: f64 = vm.call(i as f64, "+(_)", (1.0,)).unwrap();
+		assert_eq!(sum, i as f64 + 1.0);
+	}
+	assert_eq!(vm.call::<f64, _, _>(2.0, "-", ()).unwrap(), -2.0);
+	assert_eq!(vm.call::<usize, _, _>(vec![1.0, 2.0], "count", ()).unwrap(), 2);
+	assert_eq!(vm.call::<f64, _, _>(vec![1.0, 2.0], "[_]", (1,)).unwrap(), 2.0);
+	assert!(vm.call::<bool, _, _>("wren", "contains(_)", ("re",)).unwrap());
+	let joined: String = vm.call(vec!["a", "b"], "join(_)", (", ",)).unwrap();
+	assert_eq!(joined, "a, b");
+
+	assert!(matches!(vm.call::<f64, _, _>(1.0, "+(_)", ()), Err(WrenError::Arity { arity: 1, args: 0, .. })));
+	assert!(matches!(vm.call::<f64, _, _>(1.0, "+(_", (1.0,)), Err(WrenError::Signature(_))));
+	match vm.call::<(), _, _>(1.0, "missing()", ()) {
+		Err(WrenError::Runtime { message, .. }) => assert!(message.contains("missing()")),
+		other => panic!("unexpected {:?}", other),
+	}
+	assert!(matches!(vm.call::<String, _, _>(1.0, "+(_)", (1.0,)), Err(WrenError::Slot(SlotError::Type { .. }))));
+}
+
+#[test]
+fn variables() {
+	let (mut vm, output) = vm_with(VmBuilder::new());
+	let source = "class Game {\n\
+		static greet(name) { System.print(\"Hello, %(name)!\") }\n\
+		static count { __count }\n\
+		static count=(value) { __count = value }\n\
+	}\n\
+	var Answer = 42\n";
+	vm.interpret("main", source).unwrap();
+
+	assert!(vm.has_variable("main", "Game"));
+	assert!(!vm.has_variable("main", "Missing"));
+	assert!(!vm.has_variable("missing", "Game"));
+	assert_eq!(vm.get_variable::<i32>("main", "Answer").unwrap(), 42);
+
+	let game: Handle = vm.get_variable("main", "Game").unwrap();
+	vm.call::<(), _, _>(&game, "greet(_)", ("Wren",)).unwrap();
+	assert_eq!(*output.borrow(), "Hello, Wren!\n");
+	vm.call::<(), _, _>(&game, "count=(_)", (3,)).unwrap();
+	assert_eq!(vm.call::<f64, _, _>(&game, "count", ()).unwrap(), 3.0);
+
+	assert!(matches!(
+		vm.get_variable::<Handle>("main", "Missing"),
+		Err(WrenError::UnknownVariable { .. })
+	));
+	assert!(matches!(vm.get_variable::<Handle>("missing", "Game"), Err(WrenError::UnknownModule(_))));
+	assert!(matches!(vm.get_variable::<String>("main", "Answer"), Err(WrenError::Slot(_))));
+}
+
+#[test]
+fn foreign_methods() {
+	let mut registry = ForeignRegistry::new();
+	registry.method("main", "Math", true, "add(_,_)", |slots| {
+		let sum = slots.get::<f64>(1)? + slots.get::<f64>(2)?;
+		Ok(slots.set(0, sum)?)
+	});
+	registry.method("main", "Math", true, "fail()", |_| Err("failed".into()));
+	let (mut vm, output) = vm_with(VmBuilder::new().foreign(registry));
+	let source = "class Math {\n\
+		foreign static add(a, b)\n\
+		foreign static fail()\n\
+		foreign static missing()\n\
+	}\n\
+	System.print(Math.add(1, 2))\n";
+	vm.interpret("main", source).unwrap();
+	assert_eq!(*output.borrow(), "3\n");
+
+	match vm.interpret("main", "Math.fail()") {
+		Err(WrenError::Runtime { message, .. }) => assert_eq!(message, "failed"),
+		other => panic!("unexpected {:?}", other),
+	}
+	match vm.interpret("main", "Math.add(\"one\", 2)") {
+		Err(WrenError::Runtime { .. }) => {}
+		other => panic!("unexpected {:?}", other),
+	}
+	match vm.interpret("main", "class Other {\n\tforeign static missing()\n}\nOther.missing()") {
+		Err(WrenError::UnboundMethod(key)) => {
+			assert_eq!(key.class, "Other");
+			assert_eq!(key.signature, "missing()");
+			assert!(key.is_static);
+		}
+		other => panic!("unexpected {:?}", other),
+	}
+}
+
+#[test]
+fn foreign_panics() {
+	let mut registry = ForeignRegistry::new();
+	registry.method("main", "Math", true, "panic()", |_| panic!("from Rust"));
+	let mut vm = VmBuilder::new().foreign(registry).build();
+
+	let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+		vm.interpret("main", "class Math {\n\tforeign static panic()\n}\nMath.panic()")
+	}));
+	let payload = result.unwrap_err();
+	assert_eq!(payload.downcast_ref::<&str>(), Some(&"from Rust"));
+}
+
+#[test]
+fn foreign_closures() {
+	let calls = Rc::new(Cell::new(0));
+	let counted = calls.clone();
+	let mut registry = ForeignRegistry::new();
+	registry.method("main", "Counter", true, "next()", move |slots| {
+		counted.set(counted.get() + 1);
+		Ok(slots.set(0, counted.get())?)
+	});
+	let (mut vm, output) = vm_with(VmBuilder::new().foreign(registry));
+	let source = "class Counter {\n\
+		foreign static next()\n\
+	}\n\
+	Counter.next()\n\
+	System.print(Counter.next())\n";
+	vm.interpret("main", source).unwrap();
+	assert_eq!(*output.borrow(), "2\n");
+	assert_eq!(calls.get(), 2);
+}
+
+#[test]
+fn too_many_foreign_methods() {
+	let mut registry = ForeignRegistry::new();
+	let mut source = String::from("class Many {\n");
+	for i in 0..=1024 {
+		let signature = format!("m{}()", i);
+		registry.method("main", "Many", true, &signature, move |slots| Ok(slots.set(0, i)?));
+		source.push_str(&format!("\tforeign static m{}()\n", i));
+	}
+	source.push_str("}\n");
+	registry.class("main", "Late", |_| Ok(()));
+	let mut vm = VmBuilder::new().foreign(registry).build();
+
+	match vm.interpret("main", &source) {
+		Err(WrenError::TooManyForeignMethods(key)) => assert_eq!(key.signature, "m1024()"),
+		other => panic!("unexpected {:?}", other),
+	}
+	match vm.interpret("main", "foreign class Late {\n\tconstruct new() {}\n}\nLate.new()") {
+		Err(WrenError::TooManyForeignMethods(key)) => assert_eq!(key.signature, "<allocate>"),
+		other => panic!("unexpected {:?}", other),
+	}
+}
+
+struct Counter {
+	count: f64,
+	drops: Rc<Cell<usize>>,
+}
+
+impl Drop for Counter {
+	fn drop(&mut self) {
+		self.drops.set(self.drops.get() + 1);
+	}
+}
+
+const COUNTER: &str = "foreign class Counter {\n\
+	construct new(count) {}\n\
+	foreign increment()\n\
+	foreign count\n\
+	foreign static starting(count)\n\
+}\n";
+
+fn counters(drops: &Rc<Cell<usize>>) -> ForeignRegistry {
+	let mut registry = ForeignRegistry::new();
+	let constructed = drops.clone();
+	registry.class("main", "Counter", move |slots| {
+		Ok(Counter {
+			count: slots.get(1)?,
+			drops: constructed.clone(),
+		})
+	});
+	registry.method("main", "Counter", false, "increment()", |slots| {
+		slots.get_foreign::<Counter>(0)?.count += 1.0;
+		Ok(())
+	});
+	registry.method("main", "Counter", false, "count", |slots| {
+		let count = slots.get_foreign::<Counter>(0)?.count;
+		Ok(slots.set(0, count)?)
+	});
+	let made = drops.clone();
+	registry.method("main", "Counter", true, "starting(_)", move |slots| {
+		let count = slots.get(1)?;
+		let counter = Counter { count, drops: made.clone() };
+		Ok(slots.set_foreign(0, "main", "Counter", counter)?)
+	});
+	registry
+}
+
+#[test]
+fn foreign_classes() {
+	let drops = Rc::new(Cell::new(0));
+	let (mut vm, output) = vm_with(VmBuilder::new().foreign(counters(&drops)));
+	vm.interpret("main", COUNTER).unwrap();
+	let source = "var a = Counter.new(1)\n\
+		a.increment()\n\
+		var b = Counter.starting(10)\n\
+		b.increment()\n\
+		System.print([a.count, b.count])\n\
+		a = null\n";
+	vm.interpret("main", source).unwrap();
+	assert_eq!(*output.borrow(), "[2, 11]\n");
+
+	// `a` is garbage, `b` is still held by the module.
+	vm.collect_garbage();
+	assert_eq!(drops.get(), 1);
+	drop(vm);
+	assert_eq!(drops.get(), 2);
+}
+
+#[test]
+fn foreign_errors() {
+	let drops = Rc::new(Cell::new(0));
+	let mut vm = VmBuilder::new().foreign(counters(&drops)).build();
+	vm.interpret("main", COUNTER).unwrap();
+
+	match vm.interpret("main", "Counter.new(\"one\")") {
+		Err(WrenError::Runtime { .. }) => {}
+		other => panic!("unexpected {:?}", other),
+	}
+	match vm.interpret("main", "foreign class Other {\n\tconstruct new() {}\n}\nOther.new()") {
+		Err(WrenError::Runtime { message, .. }) => assert_eq!(message, "No allocator registered for foreign class."),
+		other => panic!("unexpected {:?}", other),
+	}
+
+	// Only classes the VM bound from the registry can be created from Rust.
+	let mut slots = vm.slots();
+	slots.ensure(2).unwrap();
+	assert!(matches!(
+		slots.set_foreign(0, "main", "Missing", 1.0),
+		Err(SlotError::UnknownClass { .. })
+	));
+	assert!(matches!(
+		slots.set_foreign(0, "main", "Other", 1.0),
+		Err(SlotError::UnknownClass { .. })
+	));
+
+	slots.set_foreign(0, "main", "Counter", Counter { count: 1.0, drops: drops.clone() }).unwrap();
+	assert_eq!(slots.get_foreign::<Counter>(0).unwrap().count, 1.0);
+	match slots.get_foreign::<f64>(0) {
+		Err(SlotError::ForeignType { slot: 0, expected, found: Some(found) }) => {
+			assert_eq!(expected, "f64");
+			assert!(found.ends_with("Counter"));
+		}
+		other => panic!("unexpected {:?}", other.map(|value| *value)),
+	}
+	slots.set(1, 1.0).unwrap();
+	assert!(matches!(slots.get_foreign::<Counter>(1), Err(SlotError::Type { slot: 1, .. })));
+}
+
+#[repr(align(32))]
+struct Aligned(u64);
+
+#[test]
+fn foreign_alignment() {
+	let mut registry = ForeignRegistry::new();
+	registry.class("main", "Aligned", |slots| Ok(Aligned(slots.get(1)?)));
+	registry.method("main", "Aligned", false, "value", |slots| {
+		let aligned = slots.get_foreign::<Aligned>(0)?;
+		assert_eq!(aligned as *mut Aligned as usize % 32, 0);
+		let value = aligned.0;
+		Ok(slots.set(0, value)?)
+	});
+	let (mut vm, output) = vm_with(VmBuilder::new().foreign(registry));
+	let source = "foreign class Aligned {\n\
+		construct new(value) {}\n\
+		foreign value\n\
+	}\n\
+	var values = (1..8).map {|i| Aligned.new(i * 1000) }.toList\n\
+	System.print(values.map {|aligned| aligned.value }.toList)\n";
+	vm.interpret("main", source).unwrap();
+	assert_eq!(*output.borrow(), "[1000, 2000, 3000, 4000, 5000, 6000, 7000, 8000]\n");
+
+	let mut slots = vm.slots();
+	slots.ensure(1).unwrap();
+	for value in 0..8 {
+		slots.set_foreign(0, "main", "Aligned", Aligned(value)).unwrap();
+		let aligned = slots.get_foreign::<Aligned>(0).unwrap();
+		assert_eq!(aligned as *mut Aligned as usize % 32, 0);
+		assert_eq!(aligned.0, value);
+	}
+}
+
+#[test]
+fn declared_modules() {
+	let mut registry = ForeignRegistry::new();
+	registry.method("math", "Math", true, "add(_,_)", |slots| {
+		let sum = slots.get::<f64>(1)? + slots.get::<f64>(2)?;
+		Ok(slots.set(0, sum)?)
+	});
+	registry.class("math", "Point", |slots| Ok((slots.get::<f64>(1)?, slots.get::<f64>(2)?)));
+	registry.constructor("math", "Point", "new(_,_)");
+	registry.method("math", "Point", false, "x", |slots| {
+		let x = slots.get_foreign::<(f64, f64)>(0)?.0;
+		Ok(slots.set(0, x)?)
+	});
+	let (mut vm, output) = vm_with(VmBuilder::new().foreign(registry));
+
+	let source = "import \"math\" for Math, Point\n\
+		System.print([Math.add(1, 2), Point.new(3, 4).x])\n";
+	vm.interpret("main", source).unwrap();
+	assert_eq!(*output.borrow(), "[3, 3]\n");
+	assert!(vm.has_module("math"));
+
+	match vm.interpret("main", "import \"missing\"") {
+		Err(WrenError::Runtime { message, .. }) => assert!(message.contains("missing"), "{}", message),
+		other => panic!("unexpected {:?}", other),
+	}
+}
+
+// Serves every module as a variable holding its own name.
+struct Echo;
+
+impl ModuleLoader for Echo {
+	fn load(&mut self, name: &str) -> Option<Cow<'_, str>> {
+		Some(Cow::Owned(format!("var Name = \"{}\"", name)))
+	}
+}
+
+#[test]
+fn imports() {
+	let mut modules = MemoryLoader::new();
+	modules.insert("lib/vector", "import \"./util\" for Twice\nclass Vector {\n\tstatic twice(x) { Twice.call(x) }\n}");
+	modules.insert("lib/util", "var Twice = Fn.new {|x| x * 2 }");
+	modules.insert("broken", "var x = ");
+	let mut loader = ChainLoader::new();
+	loader.push(modules).push(Echo);
+	let (mut vm, output) = vm_with(VmBuilder::new().loader(loader));
+
+	vm.interpret("main", "import \"lib/vector\" for Vector\nSystem.print(Vector.twice(21))").unwrap();
+	assert_eq!(*output.borrow(), "42\n");
+	assert!(vm.has_module("lib/util"));
+
+	// Anything the memory loader doesn't have falls through to the next one.
+	vm.interpret("main", "import \"other\" for Name\nSystem.print(Name)").unwrap();
+	assert_eq!(*output.borrow(), "42\nother\n");
+
+	match vm.interpret("main", "import \"broken\"") {
+		Err(WrenError::Compile(diagnostics)) => assert_eq!(diagnostics[0].module, "broken"),
+		other => panic!("unexpected {:?}", other),
+	}
+
+	let mut vm = VmBuilder::new().loader(MemoryLoader::new()).build();
+	match vm.interpret("main", "import \"missing\"") {
+		Err(WrenError::Runtime { message, .. }) => assert!(message.contains("missing"), "{}", message),
+		other => panic!("unexpected {:?}", other),
+	}
+}
+
+#[cfg(feature = "meta")]
+#[test]
+fn checks_syntax() {
+	assert!(check_syntax("main", "System.print(\"fine\")").is_ok());
+	// Nothing is run, and imports aren't followed.
+	assert!(check_syntax("main", "import \"missing\" for Missing\nFiber.abort(\"never\")").is_ok());
+	// No names are declared besides the script's own.
+	assert!(check_syntax("main", "var wren_sys_check = 1\nvar Compiler = 2").is_ok());
+	assert!(check_syntax("wren_sys_check", "var wren_sys_check = 1").is_ok());
+
+	match check_syntax("main", "var x = 1\nvar y = ") {
+		Err(WrenError::Compile(diagnostics)) => {
+			assert_eq!(diagnostics[0].module, "main");
+			assert_eq!(diagnostics[0].line, 2);
+		}
+		other => panic!("unexpected {:?}", other),
+	}
+}
@@ -0,0 +1,5 @@
+use syn::Attribute;
+
+pub fn wren_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+	attrs.iter().filter(|attr| attr.path().is_ident("wren"))
+}
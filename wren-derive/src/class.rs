@@ -0,0 +1,65 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{DeriveInput, LitStr, Result};
+
+use crate::attr::wren_attrs;
+
+pub fn expand(input: DeriveInput) -> Result<TokenStream> {
+	if !input.generics.params.is_empty() {
+		return Err(syn::Error::new_spanned(&input.generics, "a `WrenClass` can't be generic"));
+	}
+
+	let mut module: Option<LitStr> = None;
+	let mut name = LitStr::new(&input.ident.to_string(), input.ident.span());
+	for attr in wren_attrs(&input.attrs) {
+		attr.parse_nested_meta(|meta| {
+			if meta.path.is_ident("module") {
+				module = Some(meta.value()?.parse()?);
+			} else if meta.path.is_ident("name") {
+				name = meta.value()?.parse()?;
+			} else {
+				return Err(meta.error("expected `module` or `name`"));
+			}
+			Ok(())
+		})?;
+	}
+
+	// Registered classes are declared by loading their module on import, so
+	// it can't default to a module a script may be interpreted as.
+	let module = module.ok_or_else(|| {
+		syn::Error::new_spanned(&input.ident, "a `WrenClass` needs `#[wren(module = \"...\")]` to name the module it's declared in")
+	})?;
+
+	let ident = &input.ident;
+	Ok(quote! {
+		impl ::wren_sys::wren::WrenClass for #ident {
+			const MODULE: &'static str = #module;
+			const NAME: &'static str = #name;
+		}
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use syn::parse_quote;
+
+	use super::*;
+
+	#[test]
+	fn requires_module() {
+		let input = parse_quote! {
+			#[wren(name = "Counter")]
+			struct Count;
+		};
+		let err = expand(input).unwrap_err().to_string();
+		assert!(err.contains("needs `#[wren(module"), "{}", err);
+
+		let input = parse_quote! {
+			#[wren(module = "counter")]
+			struct Count;
+		};
+		let output = expand(input).unwrap().to_string();
+		assert!(output.contains("\"counter\""), "{}", output);
+		assert!(output.contains("\"Count\""), "{}", output);
+	}
+}
@@ -0,0 +1,108 @@
+use std::env;
+use std::ffi::OsStr;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{LitStr, Result};
+
+pub fn expand(dir: LitStr) -> Result<TokenStream> {
+	// Relative to the crate using the macro, like `include_str!` from its root.
+	let manifest_dir = env::var_os("CARGO_MANIFEST_DIR").map(PathBuf::from).unwrap_or_default();
+	let root = manifest_dir.join(dir.value());
+
+	let mut files = Vec::new();
+	walk(&root, &root, &mut files)
+		.map_err(|err| syn::Error::new(dir.span(), format!("couldn't read `{}`: {}", root.display(), err)))?;
+	files.sort();
+
+	let names = files.iter().map(|(name, _)| name);
+	let paths = files.iter().map(|(_, path)| path);
+	Ok(quote! {{
+		#[allow(unused_mut)]
+		let mut loader = ::wren_sys::wren::MemoryLoader::new();
+		#(loader.insert(#names, ::std::include_str!(#paths));)*
+		loader
+	}})
+}
+
+// Collects the module name and path of every `.wren` file under [dir].
+// Symlinks to directories aren't followed, so a link back up the tree can't
+// send this round in circles.
+fn walk(root: &Path, dir: &Path, files: &mut Vec<(String, String)>) -> io::Result<()> {
+	for entry in fs::read_dir(dir)? {
+		let entry = entry?;
+		let path = entry.path();
+		let file_type = entry.file_type()?;
+		if file_type.is_dir() {
+			walk(root, &path, files)?;
+			continue;
+		}
+		if file_type.is_symlink() && path.is_dir() {
+			continue;
+		}
+		if path.extension() != Some(OsStr::new("wren")) {
+			continue;
+		}
+
+		let invalid = || io::Error::new(io::ErrorKind::InvalidData, format!("`{}` isn't valid UTF-8", path.display()));
+		let relative = path.strip_prefix(root).unwrap_or(&path).with_extension("");
+		let segments: Option<Vec<&str>> = relative.components().map(|component| component.as_os_str().to_str()).collect();
+		let name = segments.ok_or_else(invalid)?.join("/");
+		let path = path.to_str().ok_or_else(invalid)?.to_string();
+		files.push((name, path));
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use proc_macro2::Span;
+
+	use super::*;
+
+	fn fixture() -> PathBuf {
+		Path::new(env!("CARGO_MANIFEST_DIR")).join("../tests/fixtures/scripts")
+	}
+
+	fn names(root: &Path) -> Vec<String> {
+		let mut files = Vec::new();
+		walk(root, root, &mut files).unwrap();
+		let mut names: Vec<_> = files.into_iter().map(|(name, _)| name).collect();
+		names.sort();
+		names
+	}
+
+	#[test]
+	fn names_modules_by_path() {
+		assert_eq!(names(&fixture()), ["lib/util", "lib/vector", "main"]);
+	}
+
+	#[test]
+	fn missing_directory() {
+		let err = expand(LitStr::new("tests/fixtures/missing", Span::call_site())).unwrap_err();
+		let message = err.to_string();
+		assert!(message.starts_with("couldn't read `"), "{}", message);
+		assert!(message.contains("missing"), "{}", message);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn skips_symlinked_directories() {
+		use std::os::unix::fs::symlink;
+
+		let dir = env::temp_dir().join(format!("wren-derive-include-{}", std::process::id()));
+		fs::create_dir_all(dir.join("lib")).unwrap();
+		fs::write(dir.join("lib/vector.wren"), "").unwrap();
+		fs::write(dir.join("main.wren"), "").unwrap();
+		// A loop back to the root, and a link to a single module.
+		symlink(&dir, dir.join("lib/loop")).unwrap();
+		symlink(dir.join("main.wren"), dir.join("lib/entry.wren")).unwrap();
+
+		let found = names(&dir);
+		fs::remove_dir_all(&dir).unwrap();
+		assert_eq!(found, ["lib/entry", "lib/vector", "main"]);
+	}
+}
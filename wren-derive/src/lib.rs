@@ -0,0 +1,71 @@
+//! Derive macros for exposing Rust types to Wren as foreign classes, and for
+//! embedding Wren modules.
+//!
+//! These are re-exported from `wren_sys::wren` with the `derive` feature of
+//! `wren-sys`, and the code they generate refers to it by that name.
+
+extern crate proc_macro;
+
+mod attr;
+mod class;
+mod include;
+mod methods;
+
+use proc_macro::TokenStream;
+use syn::{parse_macro_input, DeriveInput, ItemImpl, LitStr};
+
+/// Implements `WrenClass` for a struct or enum.
+///
+/// The module the class is declared in has to be given with
+/// `#[wren(module = "...")]`, and can't be one scripts are interpreted as.
+/// The class is named after the type unless `#[wren(name = "...")]` says
+/// otherwise.
+#[proc_macro_derive(WrenClass, attributes(wren))]
+pub fn derive_wren_class(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	class::expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Implements `WrenMethods` from an inherent impl block of a `WrenClass`.
+///
+/// Every function in the block is bound, with a Wren signature worked out
+/// from its Rust one: functions taking `self` become methods, the others
+/// static methods, and each other parameter is an argument converted with
+/// `FromWren`. Returning `Self` creates a new instance of the class; other
+/// return values are converted with `ToWren`, and an `Err` aborts the fiber.
+///
+/// `#[wren(...)]` on a function changes how it is bound:
+///
+/// - `construct`: a constructor, which must return `Self`. Constructors
+///   must take different numbers of arguments.
+/// - `getter`: a getter like `x`.
+/// - `setter`: a setter like `x=(_)`, named without any `set_` prefix.
+/// - `operator = "+"`: an operator like `+(_)`, or `-` without an argument.
+/// - `index`: a subscript getter like `[_]`.
+/// - `index_set`: a subscript setter like `[_]=(_)`, the value last.
+/// - `name = "..."`: the name to use in Wren.
+/// - `skip`: don't bind the function.
+#[proc_macro_attribute]
+pub fn wren_methods(args: TokenStream, input: TokenStream) -> TokenStream {
+	if !args.is_empty() {
+		let args = proc_macro2::TokenStream::from(args);
+		return syn::Error::new_spanned(args, "`wren_methods` takes no arguments")
+			.into_compile_error()
+			.into();
+	}
+	let input = parse_macro_input!(input as ItemImpl);
+	methods::expand(input).unwrap_or_else(syn::Error::into_compile_error).into()
+}
+
+/// Bakes the `.wren` files under a directory into the binary, as a
+/// `MemoryLoader` serving each as the module named by its path without the
+/// extension, like `lib/vector`.
+///
+/// The path is relative to the crate's root. Files are tracked like with
+/// `include_str!`, but files added to the directory are only picked up once
+/// something else triggers a rebuild.
+#[proc_macro]
+pub fn include_wren_dir(input: TokenStream) -> TokenStream {
+	let dir = parse_macro_input!(input as LitStr);
+	include::expand(dir).unwrap_or_else(syn::Error::into_compile_error).into()
+}
@@ -0,0 +1,498 @@
+use std::collections::HashMap;
+
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, ToTokens};
+use syn::{FnArg, GenericArgument, Ident, ImplItem, ImplItemFn, ItemImpl, LitStr, PathArguments, Result, ReturnType, Type};
+
+use crate::attr::wren_attrs;
+
+enum Kind {
+	Method,
+	Construct,
+	Getter,
+	Setter,
+	Operator(String),
+	Index,
+	IndexSet,
+}
+
+enum Receiver {
+	None,
+	Ref,
+	Value,
+}
+
+enum Output {
+	Unit,
+	This,
+	Value,
+}
+
+struct Method {
+	ident: Ident,
+	kind: Kind,
+	name: String,
+	receiver: Receiver,
+	params: Vec<Type>,
+	fallible: bool,
+	output: Output,
+}
+
+pub fn expand(mut input: ItemImpl) -> Result<TokenStream> {
+	if let Some((_, path, _)) = &input.trait_ {
+		return Err(syn::Error::new_spanned(path, "`wren_methods` goes on an inherent impl block"));
+	}
+	if !input.generics.params.is_empty() {
+		return Err(syn::Error::new_spanned(&input.generics, "a `WrenClass` can't be generic"));
+	}
+
+	let self_ty = input.self_ty.clone();
+	let mut methods = Vec::new();
+	for item in &mut input.items {
+		if let ImplItem::Fn(function) = item {
+			if let Some(method) = Method::parse(function, &self_ty)? {
+				methods.push(method);
+			}
+		}
+	}
+
+	// Wren would only ever call one of two methods with the same signature.
+	let mut seen = HashMap::new();
+	let mut arities = HashMap::new();
+	for method in &methods {
+		let ident = &method.ident;
+		if let Kind::Construct = method.kind {
+			if let Some(other) = arities.insert(method.params.len(), ident) {
+				let message = format!("`{}` and `{}` are constructors with the same number of arguments", other, ident);
+				return Err(syn::Error::new_spanned(ident, message));
+			}
+		} else if let Some(other) = seen.insert((method.is_static(), method.signature()), ident) {
+			let message = format!("`{}` and `{}` both bind `{}`", other, ident, method.signature());
+			return Err(syn::Error::new_spanned(ident, message));
+		}
+	}
+
+	let constructors: Vec<_> = methods.iter().filter(|method| method.is_constructor()).collect();
+	let others: Vec<_> = methods.iter().filter(|method| !method.is_constructor()).collect();
+
+	let allocate = allocate(&constructors);
+	let constructor_signatures = constructors.iter().map(|method| method.signature());
+	let registrations = others.iter().map(|method| method.register());
+
+	Ok(quote! {
+		#input
+
+		impl ::wren_sys::wren::WrenMethods for #self_ty {
+			fn register(registry: &mut ::wren_sys::wren::ForeignRegistry) {
+				let module = <Self as ::wren_sys::wren::WrenClass>::MODULE;
+				let class = <Self as ::wren_sys::wren::WrenClass>::NAME;
+				registry.class::<Self, _>(module, class, #allocate);
+				#(registry.constructor(module, class, #constructor_signatures);)*
+				#(#registrations)*
+			}
+		}
+	})
+}
+
+// The allocator picks a constructor by the number of arguments it was called
+// with, which is all Wren tells it.
+fn allocate(constructors: &[&Method]) -> TokenStream {
+	if constructors.is_empty() {
+		return quote! {
+			move |_: &mut ::wren_sys::wren::Slots| {
+				::std::result::Result::Err(::std::format!("{} can only be created from Rust", class).into())
+			}
+		};
+	}
+
+	let arms = constructors.iter().map(|method| {
+		let arity = method.params.len();
+		let call = method.call();
+		quote! {
+			#arity => {
+				#call
+				::std::result::Result::Ok(result)
+			}
+		}
+	});
+	quote! {
+		move |slots: &mut ::wren_sys::wren::Slots| {
+			match slots.count() - 1 {
+				#(#arms)*
+				arity => ::std::result::Result::Err(
+					::std::format!("{} has no constructor taking {} arguments", class, arity).into()
+				),
+			}
+		}
+	}
+}
+
+impl Method {
+	fn parse(function: &mut ImplItemFn, self_ty: &Type) -> Result<Option<Self>> {
+		let mut kind = None;
+		let mut name = None;
+		let mut skip = false;
+		for attr in wren_attrs(&function.attrs) {
+			attr.parse_nested_meta(|meta| {
+				let new_kind = if meta.path.is_ident("construct") {
+					Kind::Construct
+				} else if meta.path.is_ident("getter") {
+					Kind::Getter
+				} else if meta.path.is_ident("setter") {
+					Kind::Setter
+				} else if meta.path.is_ident("operator") {
+					Kind::Operator(meta.value()?.parse::<LitStr>()?.value())
+				} else if meta.path.is_ident("index") {
+					Kind::Index
+				} else if meta.path.is_ident("index_set") {
+					Kind::IndexSet
+				} else if meta.path.is_ident("name") {
+					name = Some(meta.value()?.parse::<LitStr>()?.value());
+					return Ok(());
+				} else if meta.path.is_ident("skip") {
+					skip = true;
+					return Ok(());
+				} else {
+					return Err(meta.error("unknown `wren` option"));
+				};
+				if kind.replace(new_kind).is_some() {
+					return Err(meta.error("a function can only be bound one way"));
+				}
+				Ok(())
+			})?;
+		}
+		// `wren` isn't a real attribute outside of the derive.
+		function.attrs.retain(|attr| !attr.path().is_ident("wren"));
+		if skip {
+			return Ok(None);
+		}
+
+		let sig = &function.sig;
+		let kind = kind.unwrap_or(Kind::Method);
+		let name = name.unwrap_or_else(|| {
+			let name = sig.ident.to_string();
+			let name = name.trim_start_matches("r#");
+			match kind {
+				Kind::Setter => name.trim_start_matches("set_").to_string(),
+				_ => name.to_string(),
+			}
+		});
+
+		let receiver = match sig.receiver() {
+			None => Receiver::None,
+			Some(receiver) if receiver.colon_token.is_some() => {
+				return Err(syn::Error::new_spanned(receiver, "expected `self`, `&self` or `&mut self`"));
+			}
+			Some(receiver) if receiver.reference.is_some() => Receiver::Ref,
+			Some(_) => Receiver::Value,
+		};
+
+		let mut params = Vec::new();
+		for input in &sig.inputs {
+			if let FnArg::Typed(param) = input {
+				if let Type::Reference(reference) = &*param.ty {
+					if reference.mutability.is_some() {
+						return Err(syn::Error::new_spanned(reference, "arguments from Wren can't be borrowed mutably"));
+					}
+				}
+				params.push((*param.ty).clone());
+			}
+		}
+
+		let (fallible, output) = match &sig.output {
+			ReturnType::Default => (false, Output::Unit),
+			ReturnType::Type(_, ty) => match result_ok(ty) {
+				Some(ok) => (true, Output::of(ok, self_ty)),
+				None => (false, Output::of(ty, self_ty)),
+			},
+		};
+
+		let method = Method {
+			ident: sig.ident.clone(),
+			kind,
+			name,
+			receiver,
+			params,
+			fallible,
+			output,
+		};
+		method.check().map_err(|message| syn::Error::new_spanned(&sig.ident, message))?;
+		Ok(Some(method))
+	}
+
+	fn check(&self) -> std::result::Result<(), &'static str> {
+		let arity = self.params.len();
+		match self.kind {
+			Kind::Construct => match (&self.receiver, &self.output) {
+				(Receiver::None, Output::This) => Ok(()),
+				_ => Err("a constructor takes no `self` and returns `Self`"),
+			},
+			Kind::Getter if arity != 0 => Err("a getter takes no arguments"),
+			Kind::Setter if arity != 1 => Err("a setter takes one argument"),
+			Kind::Operator(_) if arity > 1 => Err("an operator takes at most one argument"),
+			Kind::Index if arity == 0 => Err("a subscript takes at least one argument"),
+			Kind::IndexSet if arity < 2 => Err("a subscript setter takes at least one argument and the value"),
+			_ => Ok(()),
+		}
+	}
+
+	fn is_constructor(&self) -> bool {
+		matches!(self.kind, Kind::Construct)
+	}
+
+	fn is_static(&self) -> bool {
+		matches!(self.receiver, Receiver::None)
+	}
+
+	fn signature(&self) -> String {
+		let arity = self.params.len();
+		let params = |count| vec!["_"; count].join(",");
+		match &self.kind {
+			Kind::Method | Kind::Construct => format!("{}({})", self.name, params(arity)),
+			Kind::Getter => self.name.clone(),
+			Kind::Setter => format!("{}=(_)", self.name),
+			Kind::Operator(op) if arity == 0 => op.clone(),
+			Kind::Operator(op) => format!("{}(_)", op),
+			Kind::Index => format!("[{}]", params(arity)),
+			Kind::IndexSet => format!("[{}]=(_)", params(arity - 1)),
+		}
+	}
+
+	// Reads the arguments and the receiver, calls the function and leaves
+	// what it returned in `result`. The receiver is borrowed last, since
+	// reading the arguments needs the slots too.
+	fn call(&self) -> TokenStream {
+		let mut reads = Vec::new();
+		let mut args = Vec::new();
+		for (index, ty) in self.params.iter().enumerate() {
+			let arg = format_ident!("a{}", index);
+			let slot = index + 1;
+			match ty {
+				Type::Reference(reference) => {
+					let owned = owned(&reference.elem);
+					reads.push(quote! { let #arg = slots.get::<#owned>(#slot)?; });
+					args.push(quote! { &#arg });
+				}
+				_ => {
+					reads.push(quote! { let #arg = slots.get::<#ty>(#slot)?; });
+					args.push(quote! { #arg });
+				}
+			}
+		}
+
+		match self.receiver {
+			Receiver::None => {}
+			Receiver::Ref => {
+				reads.push(quote! { let receiver = slots.get_foreign::<Self>(0)?; });
+				args.insert(0, quote! { receiver });
+			}
+			Receiver::Value => {
+				reads.push(quote! { let receiver = slots.get::<Self>(0)?; });
+				args.insert(0, quote! { receiver });
+			}
+		}
+
+		let ident = &self.ident;
+		let question = if self.fallible { quote! { ? } } else { quote! {} };
+		let call = quote! { Self::#ident(#(#args),*)#question };
+		match self.output {
+			Output::Unit => quote! { #(#reads)* #call; },
+			_ => quote! { #(#reads)* let result = #call; },
+		}
+	}
+
+	fn register(&self) -> TokenStream {
+		let is_static = self.is_static();
+		let signature = self.signature();
+		let call = self.call();
+		let store = match self.output {
+			Output::Unit => quote! { slots.set_null(0)?; },
+			Output::This => quote! { slots.set_foreign(0, module, class, result)?; },
+			Output::Value => quote! { slots.set(0, result)?; },
+		};
+		quote! {
+			registry.method(module, class, #is_static, #signature, move |slots: &mut ::wren_sys::wren::Slots| {
+				#call
+				#store
+				::std::result::Result::Ok(())
+			});
+		}
+	}
+}
+
+impl Output {
+	fn of(ty: &Type, self_ty: &Type) -> Self {
+		match ty {
+			Type::Tuple(tuple) if tuple.elems.is_empty() => Output::Unit,
+			Type::Path(path) if path.qself.is_none() && path.path.is_ident("Self") => Output::This,
+			_ if ty.to_token_stream().to_string() == self_ty.to_token_stream().to_string() => Output::This,
+			_ => Output::Value,
+		}
+	}
+}
+
+// The `T` of a `Result<T, E>`, by name since that's all a macro can see.
+fn result_ok(ty: &Type) -> Option<&Type> {
+	let segment = match ty {
+		Type::Path(path) if path.qself.is_none() => path.path.segments.last()?,
+		_ => return None,
+	};
+	if segment.ident != "Result" {
+		return None;
+	}
+	match &segment.arguments {
+		PathArguments::AngleBracketed(args) => match args.args.first()? {
+			GenericArgument::Type(ok) => Some(ok),
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+// What to read from Wren for an argument borrowed as `&T`.
+fn owned(ty: &Type) -> TokenStream {
+	match ty {
+		Type::Path(path) if path.path.is_ident("str") => quote! { ::std::string::String },
+		Type::Slice(slice) => {
+			let elem = &slice.elem;
+			quote! { ::std::vec::Vec<#elem> }
+		}
+		_ => ty.to_token_stream(),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use syn::parse_quote;
+
+	use super::*;
+
+	fn signatures(mut input: ItemImpl) -> Vec<(bool, String)> {
+		let self_ty = input.self_ty.clone();
+		let mut signatures = Vec::new();
+		for item in &mut input.items {
+			if let ImplItem::Fn(function) = item {
+				if let Some(method) = Method::parse(function, &self_ty).unwrap() {
+					signatures.push((method.is_static(), method.signature()));
+				}
+			}
+		}
+		signatures
+	}
+
+	fn error(input: ItemImpl) -> String {
+		match expand(input) {
+			Ok(_) => panic!("expected an error"),
+			Err(err) => err.to_string(),
+		}
+	}
+
+	#[test]
+	fn signatures_from_rust() {
+		let input = parse_quote! {
+			impl Vec3 {
+				#[wren(construct)]
+				fn new(x: f64, y: f64, z: f64) -> Self { unimplemented!() }
+				#[wren(getter)]
+				fn x(&self) -> f64 { unimplemented!() }
+				#[wren(setter)]
+				fn set_x(&mut self, x: f64) {}
+				fn dot(&self, other: &Vec3) -> f64 { unimplemented!() }
+				fn origin() -> Vec3 { unimplemented!() }
+				#[wren(operator = "+")]
+				fn add(&self, other: &Vec3) -> Vec3 { unimplemented!() }
+				#[wren(operator = "-")]
+				fn neg(&self) -> Vec3 { unimplemented!() }
+				#[wren(index)]
+				fn get(&self, index: usize) -> Result<f64, String> { unimplemented!() }
+				#[wren(index_set)]
+				fn set(&mut self, row: usize, column: usize, value: f64) {}
+				#[wren(getter, name = "toString")]
+				fn describe(&self) -> String { unimplemented!() }
+				#[wren(skip)]
+				fn helper(&self) {}
+			}
+		};
+		let expected = [
+			(true, "new(_,_,_)"),
+			(false, "x"),
+			(false, "x=(_)"),
+			(false, "dot(_)"),
+			(true, "origin()"),
+			(false, "+(_)"),
+			(false, "-"),
+			(false, "[_]"),
+			(false, "[_,_]=(_)"),
+			(false, "toString"),
+		];
+		let expected: Vec<_> = expected.iter().map(|(is_static, signature)| (*is_static, signature.to_string())).collect();
+		assert_eq!(signatures(input), expected);
+	}
+
+	#[test]
+	fn strips_wren_attributes() {
+		let input = parse_quote! {
+			impl Counter {
+				#[wren(getter)]
+				#[inline]
+				fn count(&self) -> f64 { 0.0 }
+			}
+		};
+		let output = expand(input).unwrap().to_string();
+		assert!(output.contains("inline"));
+		assert!(!output.contains("getter"));
+	}
+
+	#[test]
+	fn rejects_invalid_bindings() {
+		let same_arity = error(parse_quote! {
+			impl Vec3 {
+				#[wren(construct)]
+				fn new(x: f64) -> Self { unimplemented!() }
+				#[wren(construct)]
+				fn splat(x: f64) -> Self { unimplemented!() }
+			}
+		});
+		assert!(same_arity.contains("same number of arguments"), "{}", same_arity);
+
+		let same_signature = error(parse_quote! {
+			impl Vec3 {
+				fn len(&self) -> f64 { 0.0 }
+				#[wren(name = "len")]
+				fn length(&self) -> f64 { 0.0 }
+			}
+		});
+		assert!(same_signature.contains("both bind `len()`"), "{}", same_signature);
+
+		let getter = error(parse_quote! {
+			impl Vec3 {
+				#[wren(getter)]
+				fn x(&self, scale: f64) -> f64 { 0.0 }
+			}
+		});
+		assert!(getter.contains("a getter takes no arguments"), "{}", getter);
+
+		let borrowed = error(parse_quote! {
+			impl Vec3 {
+				fn add_to(&self, other: &mut Vec3) {}
+			}
+		});
+		assert!(borrowed.contains("can't be borrowed mutably"), "{}", borrowed);
+
+		let constructor = error(parse_quote! {
+			impl Vec3 {
+				#[wren(construct)]
+				fn new(&self) -> Self { unimplemented!() }
+			}
+		});
+		assert!(constructor.contains("a constructor takes no `self`"), "{}", constructor);
+
+		let unknown = error(parse_quote! {
+			impl Vec3 {
+				#[wren(static)]
+				fn new() {}
+			}
+		});
+		assert!(unknown.contains("unknown `wren` option"), "{}", unknown);
+	}
+}